@@ -1,32 +1,27 @@
-use deepseek_rs::model::{ModelRequest, ModelResponse};
-use deepseek_rs::http::{get, process_response};
-use std::io::{self, Write};
-
-fn get_api_key() -> String {
-    print!("请输入您的 API Key: ");
-    io::stdout().flush().unwrap();
-    
-    let mut api_key = String::new();
-    io::stdin().read_line(&mut api_key).unwrap();
-    api_key.trim().to_string()
-}
+//! 运行前设置环境变量 DEEPSEEK_API_KEY
+use deepseek_rs::DeepSeekClient;
 
 #[tokio::main]
 async fn main() {
-    let url = ModelRequest::url();
-
-    println!("url: {}", url);
-    
-    let api_key = get_api_key();
+    let client = match DeepSeekClient::from_env() {
+        Ok(client) => client,
+        Err(err) => {
+            println!("error: {}", err);
+            return;
+        }
+    };
 
-    let response =  get(&url, &api_key).await;
-    match process_response(response).await{
-        Ok(response) => {
-            let data: ModelResponse = serde_json::from_str(&response).unwrap();
-            println!("response: {:?}", data);
+    match client.models().await {
+        Ok(models) => {
+            for model in models.data() {
+                println!("{} ({}) {:?}", model.id(), model.owned_by(), model.name());
+            }
         }
         Err(err) => {
             println!("error: {}", err);
         }
     }
+    if let Err(err) = client.ensure_default_model().await {
+        println!("error: {}", err);
+    }
 }
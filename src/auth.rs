@@ -0,0 +1,269 @@
+//! # API Key
+//! [`SecretKey`] 不会出现在 `Debug` / `Display` 输出中，开启 `zeroize` 特性（`client` 和 `wasm` 会启用）时释放时清零内存。
+//! 客户端每次请求都从 [`KeyProvider`] 取 key，所以可以在运行时轮换 key 而不用重新创建客户端：
+//!
+//! - [`RotatingKey`]：调用 `rotate` 替换 key
+//! - [`EnvKey`]：每次读取环境变量
+//! - [`FileKey`]：文件修改后重新读取
+//! - 闭包：`Fn() -> Option<SecretKey>`
+//! - [`KeyPool`]：多个 key 轮询或主备切换，某个 key 收到 401/402 后停用并换下一个 key 重试
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::auth::{KeyPool, PoolStrategy};
+//!
+//! let pool = KeyPool::new(["sk-primary", "sk-backup"]).strategy(PoolStrategy::Failover);
+//! let client = DeepSeekClient::with_key_provider(pool);
+//! ```
+use crate::error::DeepSeekError;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// API Key，打印时显示为 `[REDACTED]`，开启 `zeroize` 特性时释放时清零
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKey(String);
+
+impl SecretKey {
+    pub fn new(key: &str) -> Self {
+        SecretKey(key.trim().to_string())
+    }
+    // 取出原始的 key，只应在发送请求时使用
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretKey([REDACTED])")
+    }
+}
+
+impl fmt::Display for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+impl From<&str> for SecretKey {
+    fn from(key: &str) -> Self {
+        SecretKey::new(key)
+    }
+}
+
+impl From<String> for SecretKey {
+    fn from(key: String) -> Self {
+        let secret = SecretKey::new(&key);
+        #[cfg(feature = "zeroize")]
+        {
+            let mut key = key;
+            key.zeroize();
+        }
+        secret
+    }
+}
+
+// 只能反序列化，不能序列化，避免 key 被写回文件或日志
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretKey::from)
+    }
+}
+
+/// 提供 API Key，每次请求调用一次
+pub trait KeyProvider: Send + Sync {
+    fn key(&self) -> Result<SecretKey, DeepSeekError>;
+    // 接口以 401/402 拒绝了 key；返回 true 表示已经换了 key，可以立即重试
+    fn reject(&self, _key: &SecretKey, _status: u16) -> bool {
+        false
+    }
+}
+
+impl KeyProvider for SecretKey {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        Ok(self.clone())
+    }
+}
+
+impl<F: Fn() -> Option<SecretKey> + Send + Sync> KeyProvider for F {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        self().ok_or_else(|| DeepSeekError::Auth(String::from("回调没有返回 key")))
+    }
+}
+
+impl<T: KeyProvider + ?Sized> KeyProvider for Arc<T> {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        (**self).key()
+    }
+    fn reject(&self, key: &SecretKey, status: u16) -> bool {
+        (**self).reject(key, status)
+    }
+}
+
+/// 可以在运行时替换的 key，clone 之间共享同一个 key
+#[derive(Debug, Clone)]
+pub struct RotatingKey {
+    key: Arc<RwLock<SecretKey>>,
+}
+
+impl RotatingKey {
+    pub fn new(key: impl Into<SecretKey>) -> Self {
+        RotatingKey {
+            key: Arc::new(RwLock::new(key.into())),
+        }
+    }
+    // 替换 key，之后的请求使用新的 key
+    pub fn rotate(&self, key: impl Into<SecretKey>) {
+        *self.key.write().unwrap() = key.into();
+    }
+}
+
+impl KeyProvider for RotatingKey {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        Ok(self.key.read().unwrap().clone())
+    }
+}
+
+/// 每次请求时读取环境变量
+#[derive(Debug, Clone)]
+pub struct EnvKey {
+    name: String,
+}
+
+impl EnvKey {
+    pub fn new(name: &str) -> Self {
+        EnvKey { name: name.to_string() }
+    }
+}
+
+impl KeyProvider for EnvKey {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        std::env::var(&self.name)
+            .ok()
+            .map(SecretKey::from)
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| DeepSeekError::Auth(format!("环境变量 {} 没有设置", self.name)))
+    }
+}
+
+/// 从文件读取 key（去掉首尾空白），文件修改时间变化后重新读取
+#[derive(Debug)]
+pub struct FileKey {
+    path: PathBuf,
+    cached: Mutex<Option<(SystemTime, SecretKey)>>,
+}
+
+impl FileKey {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        FileKey {
+            path: path.as_ref().to_path_buf(),
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl KeyProvider for FileKey {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        let error = |err: std::io::Error| DeepSeekError::Auth(format!("无法读取 {}: {}", self.path.display(), err));
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).map_err(error)?;
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((time, key)) = cached.as_ref()
+            && *time == modified
+        {
+            return Ok(key.clone());
+        }
+        let key = SecretKey::from(std::fs::read_to_string(&self.path).map_err(error)?);
+        if key.is_empty() {
+            return Err(DeepSeekError::Auth(format!("{} 是空文件", self.path.display())));
+        }
+        *cached = Some((modified, key.clone()));
+        Ok(key)
+    }
+}
+
+/// 多个 key 的使用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoolStrategy {
+    // 依次使用每个 key
+    #[default]
+    RoundRobin,
+    // 一直使用第一个可用的 key，被拒绝后才换下一个
+    Failover,
+}
+
+/// 多个 key，收到 401/402 的 key 会被停用，直到调用 [`KeyPool::reset`]
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Vec<SecretKey>,
+    disabled: Mutex<Vec<bool>>,
+    next: AtomicUsize,
+    strategy: PoolStrategy,
+}
+
+impl KeyPool {
+    pub fn new<K: Into<SecretKey>>(keys: impl IntoIterator<Item = K>) -> Self {
+        let keys: Vec<SecretKey> = keys.into_iter().map(Into::into).collect();
+        KeyPool {
+            disabled: Mutex::new(vec![false; keys.len()]),
+            keys,
+            next: AtomicUsize::new(0),
+            strategy: PoolStrategy::default(),
+        }
+    }
+    pub fn strategy(mut self, strategy: PoolStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+    // 可用的 key 数量
+    pub fn available(&self) -> usize {
+        self.disabled.lock().unwrap().iter().filter(|d| !**d).count()
+    }
+    // 重新启用所有 key，例如充值之后
+    pub fn reset(&self) {
+        self.disabled.lock().unwrap().fill(false);
+    }
+}
+
+impl KeyProvider for KeyPool {
+    fn key(&self) -> Result<SecretKey, DeepSeekError> {
+        let disabled = self.disabled.lock().unwrap();
+        let count = self.keys.len();
+        let start = match self.strategy {
+            PoolStrategy::RoundRobin => self.next.load(Ordering::Relaxed),
+            PoolStrategy::Failover => 0,
+        };
+        let i = (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&i| !disabled[i])
+            .ok_or_else(|| DeepSeekError::Auth(String::from("所有 key 都已被停用")))?;
+        // 从选中的 key 之后继续轮询，停用的 key 不会让下一个 key 连续使用两次
+        self.next.store(i + 1, Ordering::Relaxed);
+        Ok(self.keys[i].clone())
+    }
+    fn reject(&self, key: &SecretKey, status: u16) -> bool {
+        if status != 401 && status != 402 {
+            return false;
+        }
+        let mut disabled = self.disabled.lock().unwrap();
+        if let Some(i) = self.keys.iter().position(|k| k == key) {
+            disabled[i] = true;
+        }
+        disabled.iter().any(|d| !d)
+    }
+}
@@ -1,30 +1,100 @@
 use serde::{Serialize, Deserialize};
 use std::fmt;
+use std::str::FromStr;
 
 // 模型名称
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum ModelName {
     DeepseekChat,
-    DeepseekReasoner
+    DeepseekReasoner,
+    // 其他模型，例如 OpenAI 兼容服务上的模型，见 [`crate::provider`]
+    Custom(String),
 }
 
 // 将模型名称由枚举转为字符串，方便使用
 impl fmt::Display for ModelName {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ModelName {
+    // 其他模型
+    pub fn custom(id: &str) -> Self {
+        ModelName::Custom(id.to_string())
+    }
+    // 由模型 id 转换，本库不认识的模型为 Custom
+    pub fn from_id(id: &str) -> Self {
+        id.parse().unwrap_or_else(|_| ModelName::custom(id))
+    }
+    // 接口使用的模型 id
+    pub fn as_str(&self) -> &str {
         match self {
-            ModelName::DeepseekChat => write!(f, "deepseek-chat"),
-            ModelName::DeepseekReasoner => write!(f, "deepseek-reasoner"),
+            ModelName::DeepseekChat => "deepseek-chat",
+            ModelName::DeepseekReasoner => "deepseek-reasoner",
+            ModelName::Custom(id) => id,
         }
     }
 }
 
 // 模型字符串与枚举的互相转换
-impl From<ModelName> for &str {
-    fn from(model_name: ModelName) -> Self {
-        match model_name {
-            ModelName::DeepseekChat => "deepseek-chat",
-            ModelName::DeepseekReasoner => "deepseek-reasoner",
+impl<'a> From<&'a ModelName> for &'a str {
+    fn from(model_name: &'a ModelName) -> Self {
+        model_name.as_str()
+    }
+}
+
+impl FromStr for ModelName {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deepseek-chat" => Ok(ModelName::DeepseekChat),
+            "deepseek-reasoner" => Ok(ModelName::DeepseekReasoner),
+            _ => Err(format!("未知的模型名称: {}", s)),
         }
     }
 }
 
+/// 每百万 token 的价格，默认为 deepseek-chat 与 deepseek-reasoner 的官方价格（美元），
+/// 价格调整或使用其他币种时请自行设置
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    // 输入 token（缓存命中）
+    cache_hit: f64,
+    // 输入 token（缓存未命中）
+    cache_miss: f64,
+    // 输出 token
+    output: f64,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Pricing::new(0.028, 0.28, 0.42)
+    }
+}
+
+impl Pricing {
+    pub fn new(cache_hit: f64, cache_miss: f64, output: f64) -> Self {
+        Pricing {
+            cache_hit,
+            cache_miss,
+            output,
+        }
+    }
+    pub fn cache_hit(&self) -> f64 {
+        self.cache_hit
+    }
+    pub fn cache_miss(&self) -> f64 {
+        self.cache_miss
+    }
+    pub fn output(&self) -> f64 {
+        self.output
+    }
+    // 按 token 数计算费用
+    pub fn cost(&self, cache_hit_tokens: usize, cache_miss_tokens: usize, output_tokens: usize) -> f64 {
+        (cache_hit_tokens as f64 * self.cache_hit
+            + cache_miss_tokens as f64 * self.cache_miss
+            + output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
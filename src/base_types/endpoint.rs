@@ -0,0 +1,10 @@
+//! # 接口地址
+//! 异步客户端和 wasm 客户端共用的地址和路径。
+
+/// DeepSeek 接口地址
+pub const DEFAULT_BASE_URL: &str = "https://api.deepseek.com";
+
+pub const CHAT_COMPLETIONS_PATH: &str = "/chat/completions";
+pub const FIM_COMPLETIONS_PATH: &str = "/beta/completions";
+pub const MODELS_PATH: &str = "/models";
+pub const BALANCE_PATH: &str = "/user/balance";
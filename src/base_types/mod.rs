@@ -1,3 +1,4 @@
 //! # 模型基础参数
 pub mod data;
-pub mod com_trait;
\ No newline at end of file
+pub mod com_trait;
+pub mod endpoint;
\ No newline at end of file
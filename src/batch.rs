@@ -0,0 +1,366 @@
+//! # 批量处理
+//! 从 JSONL 文件读取请求，以有限的并发执行，把结果和错误按 `custom_id` 写入输出 JSONL。
+//! 输出中已经成功（有 `response`）的 `custom_id` 会被跳过，所以进程中断后用相同的参数重新运行即可继续；
+//! 失败的请求（例如 429、5xx）在重新运行时会再次执行，新的结果追加在后面，同一个 `custom_id` 以最后一行为准。
+//!
+//! 输入的每一行是一个完整的请求，或者一组模板变量（需要通过 [`BatchRunner::template`] 设置模板）：
+//!
+//! ```text
+//! {"custom_id": "q1", "request": {"model": "deepseek-chat", "messages": [{"role": "user", "content": "你好"}]}}
+//! {"custom_id": "q2", "variables": {"topic": "Rust"}}
+//! ```
+//!
+//! 输出的每一行是 `{"custom_id": ..., "response": {...}}` 或 `{"custom_id": ..., "error": {...}}`，
+//! 顺序为完成的顺序。
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::batch::BatchRunner;
+//! use deepseek_rs::rate_limit::RateLimiter;
+//! use deepseek_rs::retry::RetryPolicy;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::batch::BatchError> {
+//! let summary = BatchRunner::new(DeepSeekClient::new("sk-..."))
+//!     .concurrency(16)
+//!     .retry(RetryPolicy::new().max_retries(5))
+//!     .rate_limiter(Arc::new(RateLimiter::new().requests_per_minute(600)))
+//!     .run("prompts.jsonl", "results.jsonl")
+//!     .await?;
+//! println!("{:?}", summary);
+//! # Ok(())
+//! # }
+//! ```
+use crate::chat::{ChatRequest, ChatResponse, RequestTemplate};
+use crate::client::DeepSeekClient;
+use crate::error::DeepSeekError;
+use crate::prompt::PromptError;
+use crate::rate_limit::{RateLimitLayer, RateLimiter};
+use crate::retry::RetryPolicy;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+/// 默认并发数
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// 输入文件中的一行
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchInput {
+    custom_id: String,
+    #[serde(flatten)]
+    body: BatchBody,
+}
+
+/// 完整的请求或模板变量
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum BatchBody {
+    Request { request: Box<ChatRequest> },
+    Variables { variables: HashMap<String, String> },
+}
+
+impl BatchInput {
+    pub fn request(custom_id: &str, request: ChatRequest) -> Self {
+        BatchInput {
+            custom_id: custom_id.to_string(),
+            body: BatchBody::Request { request: Box::new(request) },
+        }
+    }
+    pub fn variables(custom_id: &str, variables: HashMap<String, String>) -> Self {
+        BatchInput {
+            custom_id: custom_id.to_string(),
+            body: BatchBody::Variables { variables },
+        }
+    }
+    pub fn custom_id(&self) -> &str {
+        &self.custom_id
+    }
+    pub fn body(&self) -> &BatchBody {
+        &self.body
+    }
+}
+
+/// 输出文件中的一行
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchOutput {
+    custom_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<ChatResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<BatchItemError>,
+}
+
+impl BatchOutput {
+    pub fn custom_id(&self) -> &str {
+        &self.custom_id
+    }
+    pub fn response(&self) -> Option<&ChatResponse> {
+        self.response.as_ref()
+    }
+    pub fn error(&self) -> Option<&BatchItemError> {
+        self.error.as_ref()
+    }
+    pub fn is_success(&self) -> bool {
+        self.response.is_some()
+    }
+}
+
+/// 单个请求的错误
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchItemError {
+    // 接口返回的状态码，网络错误等情况为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    message: String,
+}
+
+impl BatchItemError {
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<DeepSeekError> for BatchItemError {
+    fn from(err: DeepSeekError) -> Self {
+        BatchItemError {
+            status: err.status(),
+            message: match err {
+                DeepSeekError::Api { message, .. } => message,
+                err => err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<PromptError> for BatchItemError {
+    fn from(err: PromptError) -> Self {
+        BatchItemError {
+            status: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// 批量处理的错误，单个请求的错误写入输出文件，不会中断处理
+#[derive(Debug)]
+pub enum BatchError {
+    // 读写文件失败
+    Io(std::io::Error),
+    // 输入文件第 line 行（从 1 开始）格式错误
+    Parse { line: usize, message: String },
+    // 输入文件中有重复的 custom_id
+    DuplicateId(String),
+    // 输入中有模板变量，但没有设置模板
+    MissingTemplate(String),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::Io(err) => write!(f, "读写文件失败: {}", err),
+            BatchError::Parse { line, message } => write!(f, "输入第 {} 行格式错误: {}", line, message),
+            BatchError::DuplicateId(id) => write!(f, "重复的 custom_id: {}", id),
+            BatchError::MissingTemplate(id) => write!(f, "{} 使用了模板变量，但没有设置模板", id),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BatchError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BatchError {
+    fn from(err: std::io::Error) -> Self {
+        BatchError::Io(err)
+    }
+}
+
+/// 一次运行的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    // 输入中的请求数
+    pub total: usize,
+    // 输出中已经存在而跳过的请求数
+    pub skipped: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// 批量执行器
+#[derive(Debug, Clone)]
+pub struct BatchRunner {
+    client: DeepSeekClient,
+    concurrency: usize,
+    template: Option<RequestTemplate>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl BatchRunner {
+    pub fn new(client: DeepSeekClient) -> Self {
+        BatchRunner {
+            client,
+            concurrency: DEFAULT_CONCURRENCY,
+            template: None,
+            rate_limiter: None,
+        }
+    }
+    // 同时进行的请求数，默认为 8
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    // 重试策略，替换客户端上的设置
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.retry(policy);
+        self
+    }
+    // 限流器，可以和其他任务共享；多次设置时使用最后一个
+    pub fn rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+    // 渲染模板变量输入时使用的模板
+    pub fn template(mut self, template: RequestTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// 执行 input 中的请求，把结果追加到 output，跳过 output 中已经成功的 custom_id
+    pub async fn run(&self, input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<BatchSummary, BatchError> {
+        let inputs = read_inputs(input.as_ref()).await?;
+        if self.template.is_none()
+            && let Some(input) = inputs.iter().find(|i| matches!(i.body, BatchBody::Variables { .. }))
+        {
+            return Err(BatchError::MissingTemplate(input.custom_id.clone()));
+        }
+        let done = read_done(output.as_ref()).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(output.as_ref())
+            .await?;
+        // 上次中断时最后一行可能没有写完，从新的一行开始
+        if !done.complete {
+            file.write_all(b"\n").await?;
+        }
+        let total = inputs.len();
+        let inputs: Vec<BatchInput> = inputs.into_iter().filter(|i| !done.ids.contains(&i.custom_id)).collect();
+        let mut summary = BatchSummary {
+            total,
+            skipped: total - inputs.len(),
+            ..Default::default()
+        };
+        let client = match &self.rate_limiter {
+            Some(limiter) => self.client.clone().layer(RateLimitLayer::new(limiter.clone())),
+            None => self.client.clone(),
+        };
+        let mut results = futures::stream::iter(inputs)
+            .map(|input| self.execute(&client, input))
+            .buffer_unordered(self.concurrency);
+        while let Some(output) = results.next().await {
+            if output.is_success() {
+                summary.succeeded += 1;
+            } else {
+                summary.failed += 1;
+            }
+            let mut line = serde_json::to_string(&output).map_err(std::io::Error::other)?;
+            line.push('\n');
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+        }
+        Ok(summary)
+    }
+
+    // 执行一个请求，结果不会返回错误
+    async fn execute(&self, client: &DeepSeekClient, input: BatchInput) -> BatchOutput {
+        let request = match input.body {
+            BatchBody::Request { request } => Ok(*request),
+            BatchBody::Variables { variables } => match &self.template {
+                Some(template) => template.render(&variables).map(|(_, request)| request),
+                None => unreachable!("输入在读取时已经检查过模板"),
+            },
+        };
+        let result = match request {
+            Ok(request) => client.chat(&request).await.map_err(BatchItemError::from),
+            Err(err) => Err(err.into()),
+        };
+        match result {
+            Ok(response) => BatchOutput {
+                custom_id: input.custom_id,
+                response: Some(response),
+                error: None,
+            },
+            Err(err) => BatchOutput {
+                custom_id: input.custom_id,
+                response: None,
+                error: Some(err),
+            },
+        }
+    }
+}
+
+// 读取并检查输入文件，空行会被忽略
+async fn read_inputs(path: &Path) -> Result<Vec<BatchInput>, BatchError> {
+    let text = tokio::fs::read_to_string(path).await?;
+    let mut inputs = Vec::new();
+    let mut ids = HashSet::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let input: BatchInput = serde_json::from_str(line).map_err(|e| BatchError::Parse {
+            line: index + 1,
+            message: e.to_string(),
+        })?;
+        if !ids.insert(input.custom_id.clone()) {
+            return Err(BatchError::DuplicateId(input.custom_id));
+        }
+        inputs.push(input);
+    }
+    Ok(inputs)
+}
+
+// 输出文件中已经成功的 custom_id
+struct Done {
+    ids: HashSet<String>,
+    // 文件为空或以换行结尾
+    complete: bool,
+}
+
+async fn read_done(path: &Path) -> Result<Done, BatchError> {
+    let text = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    #[derive(Deserialize)]
+    struct Line {
+        custom_id: String,
+        #[serde(default)]
+        response: Option<serde::de::IgnoredAny>,
+    }
+    // 写到一半的行无法解析，和失败的请求一样会被重新执行
+    let ids = text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Line>(line).ok())
+        .filter(|line| line.response.is_some())
+        .map(|line| line.custom_id)
+        .collect();
+    Ok(Done {
+        ids,
+        complete: text.is_empty() || text.ends_with('\n'),
+    })
+}
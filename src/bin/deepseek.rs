@@ -0,0 +1,384 @@
+//! # deepseek 命令行工具
+//! 需要开启 `cli` 特性：`cargo install deepseekClient-rs --features cli`。
+//!
+//! API Key 等设置从环境变量 `DEEPSEEK_API_KEY` 等读取，没有设置时读取配置文件
+//! （默认为 `~/.config/deepseek/config.toml`，可以通过 `--config` 指定，`--profile` 选择 profile），
+//! 格式见 `deepseek_rs::config`。
+use clap::{Parser, Subcommand, ValueEnum};
+use deepseek_rs::base_types::data::ModelName;
+use deepseek_rs::chat::*;
+use deepseek_rs::completions::FimRequestBuilder;
+use deepseek_rs::config::{ClientConfig, ConfigFile, PROFILE_ENV};
+use deepseek_rs::{DeepSeekClient, DeepSeekError};
+use futures::StreamExt;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "deepseek", version, about = "DeepSeek API 命令行工具")]
+struct Cli {
+    /// 配置文件路径
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// 配置文件中的 profile
+    #[arg(long, short, global = true)]
+    profile: Option<String>,
+    /// 接口地址
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+    /// 使用的模型：deepseek-chat 或 deepseek-reasoner
+    #[arg(long, short, global = true)]
+    model: Option<String>,
+    /// 输出格式
+    #[arg(long, short, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 交互式对话，输入 /help 查看命令
+    Chat {
+        /// 系统提示词
+        #[arg(long, short)]
+        system: Option<String>,
+    },
+    /// 单次提问，没有给出问题时从标准输入读取
+    Ask {
+        /// 问题
+        prompt: Vec<String>,
+        /// 系统提示词
+        #[arg(long, short)]
+        system: Option<String>,
+        /// 使用 JSON 模式（response_format 为 json_object），输出模型生成的 JSON 对象；
+        /// 问题或系统提示词中需要包含 "json" 字样和期望的格式
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出可用的模型
+    Models,
+    /// 查询账户余额
+    Balance,
+    /// FIM 补全，没有给出前缀时从标准输入读取
+    Fim {
+        /// 前缀
+        prompt: Option<String>,
+        /// 后缀
+        #[arg(long)]
+        suffix: Option<String>,
+        /// 最多生成的 token 数
+        #[arg(long)]
+        max_tokens: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Markdown,
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("错误: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let profile = cli.profile.or_else(|| std::env::var(PROFILE_ENV).ok());
+    let mut config = match &cli.config {
+        Some(path) => ConfigFile::load(path)?
+            .profile(profile.as_deref())?
+            .merge(ClientConfig::from_env()?),
+        None => ClientConfig::load(profile.as_deref())?,
+    };
+    if let Some(base_url) = &cli.base_url {
+        config = config.base_url(base_url);
+    }
+    if let Some(model) = &cli.model {
+        config = config.model(model);
+    }
+    let client = config.build()?;
+    let model = client.get_default_model().clone();
+    match cli.command {
+        Command::Chat { system } => chat(&client, model, system, cli.format).await,
+        Command::Ask { prompt, system, json } => ask(&client, model, prompt, system, json, cli.format).await,
+        Command::Models => models(&client, cli.format).await,
+        Command::Balance => balance(&client, cli.format).await,
+        Command::Fim {
+            prompt,
+            suffix,
+            max_tokens,
+        } => fim(&client, model, prompt, suffix, max_tokens, cli.format).await,
+    }
+}
+
+// 从参数或标准输入读取文本
+fn read_input(args: Option<String>) -> CliResult<String> {
+    match args.filter(|s| !s.is_empty() && s != "-") {
+        Some(text) => Ok(text),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn build_request(model: ModelName, system: Option<&str>, messages: &[Message]) -> ChatRequest {
+    let mut builder = ChatRequestBuilder::new().model(model);
+    if let Some(system) = system {
+        builder = builder.add_message(Message::system_message(system));
+    }
+    for message in messages {
+        builder = builder.add_message(message.clone());
+    }
+    builder.build().1
+}
+
+// 流式输出回复，返回完整的内容
+async fn stream_reply(client: &DeepSeekClient, request: &ChatRequest) -> Result<String, DeepSeekError> {
+    let mut stream = client.chat_stream(request).await?;
+    let mut content = String::new();
+    let mut stdout = io::stdout();
+    let mut reasoning = false;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let Some(choice) = chunk.choices().first() else {
+            continue;
+        };
+        // deepseek-reasoner 的推理内容输出到 stderr，不计入回复
+        if let Some(text) = choice.delta().reasoning_content().filter(|s| !s.is_empty()) {
+            reasoning = true;
+            eprint!("{}", text);
+        }
+        if let Some(text) = choice.delta().content().filter(|s| !s.is_empty()) {
+            if reasoning {
+                eprintln!();
+                reasoning = false;
+            }
+            content.push_str(text);
+            print!("{}", text);
+            let _ = stdout.flush();
+        }
+    }
+    println!();
+    Ok(content)
+}
+
+const CHAT_HELP: &str = "\
+/system <内容>  设置系统提示词，不带内容时清除
+/model <名称>   切换模型，不在内置列表中的名称按自定义模型处理
+/save <路径>    保存对话，.md 结尾保存为 markdown，否则保存为 JSON
+/clear          清空对话
+/exit           退出";
+
+// text 格式流式输出回复，json 和 markdown 格式在回复完成后按 ask 的格式输出
+async fn chat(client: &DeepSeekClient, mut model: ModelName, mut system: Option<String>, format: Format) -> CliResult<()> {
+    let mut messages: Vec<Message> = Vec::new();
+    let stdin = io::stdin();
+    eprintln!("模型 {}，输入 /help 查看命令，Ctrl-D 退出", model);
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix('/') {
+            let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+            let arg = arg.trim();
+            match name {
+                "system" => {
+                    system = Some(arg.to_string()).filter(|s| !s.is_empty());
+                    eprintln!("系统提示词已{}", if system.is_some() { "设置" } else { "清除" });
+                }
+                "model" if !arg.is_empty() => {
+                    model = ModelName::from_id(arg);
+                    eprintln!("已切换到 {}", model);
+                }
+                "save" if !arg.is_empty() => match save_history(Path::new(arg), system.as_deref(), &messages) {
+                    Ok(()) => eprintln!("已保存到 {}", arg),
+                    Err(err) => eprintln!("保存失败: {}", err),
+                },
+                "clear" => {
+                    messages.clear();
+                    eprintln!("对话已清空");
+                }
+                "exit" | "quit" => return Ok(()),
+                _ => eprintln!("{}", CHAT_HELP),
+            }
+            continue;
+        }
+        messages.push(Message::user_message(line));
+        let request = build_request(model.clone(), system.as_deref(), &messages);
+        match reply(client, &request, format).await {
+            Ok(message) => messages.push(message),
+            Err(err) => {
+                messages.pop();
+                eprintln!("错误: {}", err);
+            }
+        }
+    }
+}
+
+fn save_history(path: &Path, system: Option<&str>, messages: &[Message]) -> CliResult<()> {
+    let all: Vec<Message> = system.map(Message::system_message).into_iter().chain(messages.iter().cloned()).collect();
+    let text = if path.extension().is_some_and(|ext| ext == "md") {
+        all.iter()
+            .map(|m| format!("### {}\n\n{}\n", m.role(), m.content()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        serde_json::to_string_pretty(&all)?
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+// 按格式输出一次回复，返回可以放回对话历史的消息
+async fn reply(client: &DeepSeekClient, request: &ChatRequest, format: Format) -> CliResult<Message> {
+    if format == Format::Text {
+        let content = stream_reply(client, request).await?;
+        return Ok(Message::assistant_message(&content));
+    }
+    let response = client.chat(request).await?;
+    print_response(&response, format)?;
+    Ok(response
+        .choices
+        .first()
+        .map(|choice| choice.to_message())
+        .unwrap_or_else(|| Message::assistant_message("")))
+}
+
+fn print_response(response: &ChatResponse, format: Format) -> CliResult<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(response)?),
+        _ => {
+            println!("{}", response.content().first().copied().unwrap_or_default());
+            if let Some(usage) = response.usage() {
+                println!(
+                    "\n---\n*{} · 输入 {} tokens（缓存命中 {}）· 输出 {} tokens*",
+                    response.model(),
+                    usage.prompt_tokens(),
+                    usage.prompt_cache_hit_tokens(),
+                    usage.completion_tokens()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn ask(
+    client: &DeepSeekClient,
+    model: ModelName,
+    prompt: Vec<String>,
+    system: Option<String>,
+    json: bool,
+    format: Format,
+) -> CliResult<()> {
+    let prompt = read_input(Some(prompt.join(" ")))?;
+    let request = build_request(model, system.as_deref(), &[Message::user_message(prompt.trim())]);
+    if json {
+        let value: serde_json::Value = client.chat_json(&request).await?;
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    reply(client, &request, format).await?;
+    Ok(())
+}
+
+async fn models(client: &DeepSeekClient, format: Format) -> CliResult<()> {
+    let models = client.models().await?;
+    match format {
+        Format::Text => {
+            for model in models.data() {
+                println!("{}", model.id());
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&models)?),
+        Format::Markdown => {
+            println!("| 模型 | 所有者 |\n| --- | --- |");
+            for model in models.data() {
+                println!("| {} | {} |", model.id(), model.owned_by());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn balance(client: &DeepSeekClient, format: Format) -> CliResult<()> {
+    let balance = client.balance().await?;
+    match format {
+        Format::Text => {
+            println!("可用: {}", if balance.is_available() { "是" } else { "否" });
+            for info in balance.balance_infos() {
+                println!(
+                    "{} {}（赠金 {}，充值 {}）",
+                    info.currency(),
+                    info.total_balance(),
+                    info.granted_balance(),
+                    info.topped_up_balance()
+                );
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&balance)?),
+        Format::Markdown => {
+            println!("| 币种 | 总余额 | 赠金余额 | 充值余额 |\n| --- | --- | --- | --- |");
+            for info in balance.balance_infos() {
+                println!(
+                    "| {} | {} | {} | {} |",
+                    info.currency(),
+                    info.total_balance(),
+                    info.granted_balance(),
+                    info.topped_up_balance()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn fim(
+    client: &DeepSeekClient,
+    model: ModelName,
+    prompt: Option<String>,
+    suffix: Option<String>,
+    max_tokens: Option<usize>,
+    format: Format,
+) -> CliResult<()> {
+    let prompt = read_input(prompt)?;
+    let mut builder = FimRequestBuilder::new(&prompt).model(model);
+    if let Some(suffix) = &suffix {
+        builder = builder.suffix(suffix);
+    }
+    if let Some(max_tokens) = max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    let (_, request) = builder.build();
+    let response = client.fim(&request).await?;
+    let text = response.text().first().copied().unwrap_or_default();
+    match format {
+        Format::Text => println!("{}", text),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&response)?),
+        Format::Markdown => println!("```\n{}{}{}\n```", prompt, text, suffix.unwrap_or_default()),
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! # 同步客户端
+//! 需要启用 `blocking` 特性。[`DeepSeekClient`] 包装异步客户端，并在内部管理自己的 tokio 运行时，
+//! 适合命令行脚本、构建脚本等不使用 async 的程序。
+//!
+//! 不要在异步运行时中调用（例如 `#[tokio::main]` 的函数里），tokio 不允许在运行时中阻塞等待另一个运行时，会直接 panic；
+//! 这种情况下请直接使用异步的 [`crate::DeepSeekClient`]。
+//!
+//! ```no_run
+//! use deepseek_rs::blocking::DeepSeekClient;
+//! use deepseek_rs::chat::*;
+//!
+//! # fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let client = DeepSeekClient::new("sk-...");
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .add_message(Message::user_message("你好"))
+//!     .build();
+//! println!("{:?}", client.chat(&request)?.content());
+//! for chunk in client.chat_stream(&request)? {
+//!     print!("{}", chunk?.content().concat());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::chat::{ChatCompletionChunk, ChatRequest, ChatResponse, ToolCall};
+use crate::completions::{FimRequest, FimResponse};
+use crate::config::ConfigError;
+use crate::error::DeepSeekError;
+use crate::model::{ModelInfo, ModelResponse};
+use crate::user::BalanceResponse;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+/// 同步客户端，clone 之间共享运行时和连接池
+#[derive(Clone)]
+pub struct DeepSeekClient {
+    inner: crate::DeepSeekClient,
+    runtime: Arc<Runtime>,
+}
+
+impl fmt::Debug for DeepSeekClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeepSeekClient").field("inner", &self.inner).finish()
+    }
+}
+
+impl DeepSeekClient {
+    pub fn new(api_key: &str) -> Self {
+        Self::from_client(crate::DeepSeekClient::new(api_key))
+    }
+    // 从环境变量和配置文件创建，见 [`crate::config`]
+    pub fn from_env() -> Result<Self, ConfigError> {
+        crate::DeepSeekClient::from_env().map(Self::from_client)
+    }
+    /// 包装已经配置好的异步客户端，中间件、重试、超时等设置都会保留。
+    /// 无法创建运行时（例如无法创建线程）时 panic
+    pub fn from_client(inner: crate::DeepSeekClient) -> Self {
+        // 使用一个工作线程，流式响应在两次 next 之间也能继续接收
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("deepseek-blocking")
+            .enable_all()
+            .build()
+            .expect("无法创建 tokio 运行时");
+        DeepSeekClient {
+            inner,
+            runtime: Arc::new(runtime),
+        }
+    }
+    // 内部的异步客户端
+    pub fn inner(&self) -> &crate::DeepSeekClient {
+        &self.inner
+    }
+    // 在内部运行时中执行任意 future，用于调用这里没有包装的异步接口
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, DeepSeekError> {
+        self.block_on(self.inner.chat(request))
+    }
+    // 见 [`crate::DeepSeekClient::chat_json`]
+    pub fn chat_json<T: DeserializeOwned>(&self, request: &ChatRequest) -> Result<T, DeepSeekError> {
+        self.block_on(self.inner.chat_json(request))
+    }
+    // 见 [`crate::DeepSeekClient::run_tools`]，call 同步执行每个 tool 调用并返回结果
+    pub fn run_tools(
+        &self,
+        request: &ChatRequest,
+        max_rounds: usize,
+        mut call: impl FnMut(&ToolCall) -> String,
+    ) -> Result<ChatResponse, DeepSeekError> {
+        self.block_on(self.inner.run_tools(request, max_rounds, |tool_call| {
+            let output = call(&tool_call);
+            async move { output }
+        }))
+    }
+    // 流式 chat，返回逐个产生数据块的迭代器
+    pub fn chat_stream(&self, request: &ChatRequest) -> Result<StreamIter<ChatCompletionChunk>, DeepSeekError> {
+        let stream = self.block_on(self.inner.chat_stream(request))?;
+        Ok(StreamIter::new(stream, self.runtime.clone()))
+    }
+    pub fn fim(&self, request: &FimRequest) -> Result<FimResponse, DeepSeekError> {
+        self.block_on(self.inner.fim(request))
+    }
+    pub fn fim_stream(&self, request: &FimRequest) -> Result<StreamIter<FimResponse>, DeepSeekError> {
+        let stream = self.block_on(self.inner.fim_stream(request))?;
+        Ok(StreamIter::new(stream, self.runtime.clone()))
+    }
+    pub fn models(&self) -> Result<ModelResponse, DeepSeekError> {
+        self.block_on(self.inner.models())
+    }
+    pub fn refresh_models(&self) -> Result<ModelResponse, DeepSeekError> {
+        self.block_on(self.inner.refresh_models())
+    }
+    pub fn ensure_model(&self, model: &str) -> Result<ModelInfo, DeepSeekError> {
+        self.block_on(self.inner.ensure_model(model))
+    }
+    pub fn balance(&self) -> Result<BalanceResponse, DeepSeekError> {
+        self.block_on(self.inner.balance())
+    }
+}
+
+/// 流式响应的同步迭代器，每次 `next` 阻塞到下一个数据块；提前丢弃会关闭连接
+pub struct StreamIter<T> {
+    stream: BoxStream<'static, Result<T, DeepSeekError>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> StreamIter<T> {
+    fn new(stream: BoxStream<'static, Result<T, DeepSeekError>>, runtime: Arc<Runtime>) -> Self {
+        StreamIter { stream, runtime }
+    }
+}
+
+impl<T> fmt::Debug for StreamIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamIter").finish_non_exhaustive()
+    }
+}
+
+impl<T> Iterator for StreamIter<T> {
+    type Item = Result<T, DeepSeekError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
@@ -0,0 +1,356 @@
+//! # 响应缓存
+//! 需要开启 `cache` 特性。以接口地址和 `ChatRequest` 规范化后的 JSON（忽略 `stream`）的 SHA-256 作为键缓存 `ChatResponse`，
+//! 相同的请求（例如 temperature 为 0 的评测重跑）不会再次调用接口；不同服务之间共享存储时，同名模型的响应不会混在一起。
+//! 缓存可以放在内存中（LRU + TTL），也可以放在磁盘目录中，每个响应一个文件。
+//! 命中时流式请求会把缓存的响应作为合成的 chunk 流返回。
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::cache::{CacheControl, CachedClient, MemoryStore};
+//! use deepseek_rs::chat::*;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let client = CachedClient::new(
+//!     DeepSeekClient::new("sk-..."),
+//!     MemoryStore::new(1000).ttl(Duration::from_secs(3600)),
+//! );
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .add_message(Message::user_message("你好"))
+//!     .temperature(0.0)
+//!     .build();
+//! let response = client.chat(&request).await?;
+//! // 忽略缓存重新请求，并更新缓存
+//! let response = client.chat_with(&request, CacheControl::Refresh).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::chat::{canonicalize, replay_chunks, ChatRequest, ChatResponse, ChatStream};
+use crate::client::DeepSeekClient;
+use crate::error::DeepSeekError;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 请求的缓存键：接口地址和规范化（对象的键排序、去掉 `stream`）后的 JSON 的 SHA-256，十六进制表示
+pub fn cache_key(base_url: &str, request: &ChatRequest) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(request)?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove("stream");
+    }
+    let canonical = canonicalize(value).to_string();
+    let mut hasher = Sha256::new();
+    hasher.update(base_url.trim_end_matches('/').as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical.as_bytes());
+    let digest = hasher.finalize();
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 每次调用的缓存行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheControl {
+    // 先读缓存，未命中时请求并写入缓存
+    #[default]
+    Default,
+    // 不读也不写缓存
+    Bypass,
+    // 不读缓存，请求后写入缓存
+    Refresh,
+    // 只读缓存，未命中时请求但不写入缓存
+    ReadOnly,
+}
+
+impl CacheControl {
+    fn read(self) -> bool {
+        matches!(self, CacheControl::Default | CacheControl::ReadOnly)
+    }
+    fn write(self) -> bool {
+        matches!(self, CacheControl::Default | CacheControl::Refresh)
+    }
+}
+
+/// 缓存存储
+pub trait CacheStore: Send + Sync {
+    // 读取未过期的响应
+    fn get(&self, key: &str) -> Option<ChatResponse>;
+    fn put(&self, key: &str, response: &ChatResponse);
+    fn remove(&self, key: &str);
+    fn clear(&self);
+}
+
+impl<T: CacheStore + ?Sized> CacheStore for Arc<T> {
+    fn get(&self, key: &str) -> Option<ChatResponse> {
+        (**self).get(key)
+    }
+    fn put(&self, key: &str, response: &ChatResponse) {
+        (**self).put(key, response)
+    }
+    fn remove(&self, key: &str) {
+        (**self).remove(key)
+    }
+    fn clear(&self) {
+        (**self).clear()
+    }
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    response: ChatResponse,
+    inserted: Instant,
+    // 最近一次使用的序号，对应 order 中的键
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, MemoryEntry>,
+    // 使用序号 -> 键，最小的是最久没有使用的
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.order.remove(&entry.used);
+            entry.used = self.tick;
+            self.order.insert(self.tick, key.to_string());
+        }
+    }
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+}
+
+/// 内存缓存，超过容量时淘汰最久没有使用的响应
+#[derive(Debug)]
+pub struct MemoryStore {
+    capacity: usize,
+    ttl: Option<Duration>,
+    lru: Mutex<Lru>,
+}
+
+impl MemoryStore {
+    // 最多缓存 capacity 个响应，默认不过期
+    pub fn new(capacity: usize) -> Self {
+        MemoryStore {
+            capacity: capacity.max(1),
+            ttl: None,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+    // 响应的有效期
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<ChatResponse> {
+        let mut lru = self.lru.lock().unwrap();
+        let expired = lru
+            .entries
+            .get(key)
+            .map(|entry| self.ttl.is_some_and(|ttl| entry.inserted.elapsed() > ttl))?;
+        if expired {
+            lru.remove(key);
+            return None;
+        }
+        lru.touch(key);
+        lru.entries.get(key).map(|entry| entry.response.clone())
+    }
+    fn put(&self, key: &str, response: &ChatResponse) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.remove(key);
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+        lru.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                response: response.clone(),
+                inserted: Instant::now(),
+                used: 0,
+            },
+        );
+        lru.touch(key);
+    }
+    fn remove(&self, key: &str) {
+        self.lru.lock().unwrap().remove(key);
+    }
+    fn clear(&self) {
+        *self.lru.lock().unwrap() = Lru::default();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileEntry {
+    // 写入时间，Unix 时间戳（秒）
+    created: u64,
+    response: ChatResponse,
+}
+
+/// 磁盘缓存，目录下每个响应一个 `<key>.json` 文件，进程重启后仍然有效
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl FileStore {
+    // 目录不存在时会在第一次写入时创建
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        FileStore {
+            dir: dir.as_ref().to_path_buf(),
+            ttl: None,
+        }
+    }
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl CacheStore for FileStore {
+    fn get(&self, key: &str) -> Option<ChatResponse> {
+        let text = std::fs::read_to_string(self.path(key)).ok()?;
+        let entry: FileEntry = serde_json::from_str(&text).ok()?;
+        if self
+            .ttl
+            .is_some_and(|ttl| unix_now().saturating_sub(entry.created) > ttl.as_secs())
+        {
+            self.remove(key);
+            return None;
+        }
+        Some(entry.response)
+    }
+    // 写入失败时忽略，缓存不影响请求本身
+    fn put(&self, key: &str, response: &ChatResponse) {
+        let entry = FileEntry {
+            created: unix_now(),
+            response: response.clone(),
+        };
+        let Ok(text) = serde_json::to_string(&entry) else {
+            return;
+        };
+        // 先写临时文件再改名，避免其他进程读到写了一半的文件
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        let _ = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&tmp, text))
+            .and_then(|_| std::fs::rename(&tmp, self.path(key)));
+    }
+    fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path(key));
+    }
+    fn clear(&self) {
+        if let Ok(entries) = std::fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// 带缓存的客户端，没有命中缓存时通过内部的 [`DeepSeekClient`] 请求
+#[derive(Clone)]
+pub struct CachedClient {
+    client: DeepSeekClient,
+    store: Arc<dyn CacheStore>,
+    control: CacheControl,
+}
+
+impl fmt::Debug for CachedClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachedClient")
+            .field("client", &self.client)
+            .field("control", &self.control)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CachedClient {
+    pub fn new(client: DeepSeekClient, store: impl CacheStore + 'static) -> Self {
+        CachedClient {
+            client,
+            store: Arc::new(store),
+            control: CacheControl::Default,
+        }
+    }
+    // chat 和 chat_stream 使用的缓存行为
+    pub fn control(mut self, control: CacheControl) -> Self {
+        self.control = control;
+        self
+    }
+    pub fn client(&self) -> &DeepSeekClient {
+        &self.client
+    }
+    pub fn store(&self) -> &dyn CacheStore {
+        self.store.as_ref()
+    }
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, DeepSeekError> {
+        self.chat_with(request, self.control).await
+    }
+    // 指定本次调用的缓存行为
+    pub async fn chat_with(&self, request: &ChatRequest, control: CacheControl) -> Result<ChatResponse, DeepSeekError> {
+        let key = cache_key(self.client.get_base_url(), request)?;
+        if control.read()
+            && let Some(response) = self.store.get(&key)
+        {
+            return Ok(response);
+        }
+        let response = self.client.chat(request).await?;
+        if control.write() {
+            self.store.put(&key, &response);
+        }
+        Ok(response)
+    }
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, DeepSeekError> {
+        self.chat_stream_with(request, self.control).await
+    }
+    // 命中缓存时返回合成的 chunk 流；未命中时直接返回接口的流，流式响应不会写入缓存
+    pub async fn chat_stream_with(&self, request: &ChatRequest, control: CacheControl) -> Result<ChatStream, DeepSeekError> {
+        if control.read()
+            && let Some(response) = self.store.get(&cache_key(self.client.get_base_url(), request)?)
+        {
+            let chunks = replay_chunks(&response)?;
+            return Ok(futures::stream::iter(chunks.into_iter().map(Ok)).boxed());
+        }
+        self.client.chat_stream(request).await
+    }
+}
@@ -0,0 +1,152 @@
+//! # 取消请求
+//! [`CancelToken`] 需要启用 `client` 特性。把 [`CancelToken`] 设置到请求上（`ChatRequestBuilder::cancel_token`、`FimRequestBuilder::cancel_token`、
+//! `HttpRequest::cancel_token`），调用 [`CancelToken::cancel`] 后：
+//!
+//! - 还没有收到响应头的请求立即返回 [`DeepSeekError::Cancelled`]，正在等待的重试也会停止
+//! - 已经返回的响应体或流在后台立即关闭连接（即使没有人继续读取），之后读取时返回 `Cancelled`
+//!
+//! 关闭连接后服务器不再继续生成，也就不再计费。流式输出的部分内容可以用 [`ChatAccumulator`] 收集，
+//! 取消时得到 finish_reason 为 [`FINISH_REASON_CANCELLED`] 的响应，仍然可以保存到对话历史中。
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::cancel::CancelToken;
+//! use deepseek_rs::chat::*;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let client = DeepSeekClient::new("sk-...");
+//! let token = CancelToken::new();
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .add_message(Message::user_message("写一篇长文"))
+//!     .cancel_token(token.clone())
+//!     .build();
+//! // 用户点击“停止生成”时在其他任务中调用
+//! let stop = token.clone();
+//! tokio::spawn(async move { stop.cancel() });
+//! let response = ChatAccumulator::collect(client.chat_stream(&request).await?).await?;
+//! println!("{:?} {:?}", response.content(), response.choices[0].finish_reason());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekError::Cancelled`]: crate::DeepSeekError::Cancelled
+//! [`ChatAccumulator`]: crate::chat::ChatAccumulator
+//! [`FINISH_REASON_CANCELLED`]: crate::chat::FINISH_REASON_CANCELLED
+#[cfg(feature = "client")]
+use crate::http::ByteStream;
+#[cfg(feature = "client")]
+use futures::StreamExt;
+use std::fmt;
+use std::io;
+#[cfg(feature = "client")]
+use std::sync::Arc;
+#[cfg(feature = "client")]
+use tokio::sync::{mpsc, watch};
+
+/// 取消令牌，clone 之间共享状态，取消后不能恢复
+#[cfg(feature = "client")]
+#[derive(Clone)]
+pub struct CancelToken {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+#[cfg(feature = "client")]
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancelToken").field("cancelled", &self.is_cancelled()).finish()
+    }
+}
+
+#[cfg(feature = "client")]
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "client")]
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+    // 等待取消
+    pub async fn cancelled(&self) {
+        let mut receiver = self.sender.subscribe();
+        // sender 由 self 持有，不会关闭
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// 请求被取消。响应体字节流只能返回 `std::io::Error`，取消时以 `io::Error::new(io::ErrorKind::Interrupted, CancelledError)`
+/// 返回，客户端会把它转换为 `DeepSeekError::Cancelled`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CancelledError;
+
+impl fmt::Display for CancelledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "请求已取消")
+    }
+}
+
+impl std::error::Error for CancelledError {}
+
+impl From<CancelledError> for io::Error {
+    fn from(err: CancelledError) -> Self {
+        io::Error::new(io::ErrorKind::Interrupted, err)
+    }
+}
+
+impl CancelledError {
+    pub fn is_cancelled(err: &io::Error) -> bool {
+        err.get_ref().is_some_and(|e| e.is::<CancelledError>())
+    }
+}
+
+#[cfg(feature = "client")]
+// 在后台读取响应体，取消时立即丢弃响应体（关闭连接），不依赖调用方继续读取
+pub(crate) fn guard_body(body: ByteStream, token: CancelToken) -> ByteStream {
+    let (sender, mut receiver) = mpsc::channel(16);
+    let reader = token.clone();
+    tokio::spawn(async move {
+        let mut body = body;
+        loop {
+            // 调用方丢弃了流时同样立即关闭连接
+            let chunk = tokio::select! {
+                _ = reader.cancelled() => break,
+                _ = sender.closed() => break,
+                chunk = body.next() => chunk,
+            };
+            let Some(chunk) = chunk else { break };
+            tokio::select! {
+                _ = reader.cancelled() => break,
+                sent = sender.send(chunk) => if sent.is_err() { break },
+            }
+        }
+    });
+    Box::pin(async_stream::stream! {
+        loop {
+            let chunk = tokio::select! {
+                biased;
+                _ = token.cancelled() => None,
+                chunk = receiver.recv() => chunk,
+            };
+            match chunk {
+                Some(chunk) => yield chunk,
+                None => {
+                    if token.is_cancelled() {
+                        yield Err(CancelledError.into());
+                    }
+                    break;
+                }
+            }
+        }
+    })
+}
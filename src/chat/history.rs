@@ -0,0 +1,299 @@
+//! # 对话历史
+//! DeepSeek 的硬盘缓存按请求前缀命中：只有与之前请求逐字节相同的前缀才按缓存命中价格计费
+//! （见 `Usage::prompt_cache_hit_tokens`）。[`Conversation`] 固定系统提示词和 tools 的位置与顺序，
+//! 规范化 tools 参数的 JSON，只在末尾追加消息；修改已发送过的前缀时发出警告，并统计每个对话的缓存命中率。
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::chat::*;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let client = DeepSeekClient::new("sk-...");
+//! let mut conversation = Conversation::new(ChatRequestBuilder::new())
+//!     .system_prompt("你是一个翻译助手")
+//!     .on_invalidate(|b| eprintln!("缓存前缀失效: {}", b));
+//! conversation.send(&client, "翻译：你好").await?;
+//! conversation.send(&client, "翻译：再见").await?;
+//! println!("命中率 {:.1}%", conversation.stats().hit_ratio() * 100.0);
+//! # Ok(())
+//! # }
+//! ```
+use super::request::*;
+use super::response::{ChatResponse, Usage};
+#[cfg(feature = "client")]
+use crate::client::DeepSeekClient;
+use crate::error::DeepSeekError;
+use std::fmt;
+use std::sync::Arc;
+
+/// 把 JSON 对象的键按字典序重新排列，保证相同的内容总是序列化为相同的字节
+pub fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            serde_json::Value::Object(entries.into_iter().map(|(k, v)| (k, canonicalize(v))).collect())
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(canonicalize).collect()),
+        value => value,
+    }
+}
+
+/// 缓存前缀失效的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixBreak {
+    // 系统提示词变了，整个前缀失效
+    SystemChanged,
+    // tools 变了，整个前缀失效
+    ToolsChanged,
+    // 第 index 条消息（不含系统提示词，从 0 开始）被修改或删除，之后的前缀失效
+    MessageChanged { index: usize },
+}
+
+impl fmt::Display for PrefixBreak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrefixBreak::SystemChanged => write!(f, "系统提示词被修改"),
+            PrefixBreak::ToolsChanged => write!(f, "tools 被修改"),
+            PrefixBreak::MessageChanged { index } => write!(f, "第 {} 条消息被修改或删除", index),
+        }
+    }
+}
+
+/// 对话的缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub requests: usize,
+    pub hit_tokens: usize,
+    pub miss_tokens: usize,
+    pub completion_tokens: usize,
+}
+
+impl CacheStats {
+    // 输入 token 中缓存命中的比例，没有请求时为 0
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hit_tokens + self.miss_tokens;
+        if total == 0 {
+            0.0
+        } else {
+            self.hit_tokens as f64 / total as f64
+        }
+    }
+    pub fn record(&mut self, usage: &Usage) {
+        self.requests += 1;
+        self.hit_tokens += usage.prompt_cache_hit_tokens().max(0) as usize;
+        self.miss_tokens += usage.prompt_cache_miss_tokens().max(0) as usize;
+        self.completion_tokens += usage.completion_tokens().max(0) as usize;
+    }
+}
+
+// 上一次发送的请求中各部分序列化后的内容
+#[derive(Debug, Clone)]
+struct SentPrefix {
+    system: Option<String>,
+    tools: String,
+    messages: Vec<String>,
+}
+
+type InvalidateCallback = Arc<dyn Fn(&PrefixBreak) + Send + Sync>;
+
+/// 对前缀缓存友好的对话
+#[derive(Clone)]
+pub struct Conversation {
+    // 模型、采样参数等设置，不包含消息和 tools
+    builder: ChatRequestBuilder,
+    system: Option<Message>,
+    // 按名称排序
+    tools: Vec<Tool>,
+    messages: Vec<Message>,
+    sent: Option<SentPrefix>,
+    stats: CacheStats,
+    on_invalidate: Option<InvalidateCallback>,
+}
+
+impl fmt::Debug for Conversation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Conversation")
+            .field("system", &self.system)
+            .field("tools", &self.tools)
+            .field("messages", &self.messages)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Conversation {
+    fn default() -> Self {
+        Self::new(ChatRequestBuilder::new())
+    }
+}
+
+impl Conversation {
+    // builder 只提供模型、采样参数等设置，其中的消息和 tools 会被去掉，
+    // 请使用 system_prompt、tool 和 push 添加
+    pub fn new(builder: ChatRequestBuilder) -> Self {
+        Conversation {
+            builder: builder.without_messages(),
+            system: None,
+            tools: Vec::new(),
+            messages: Vec::new(),
+            sent: None,
+            stats: CacheStats::default(),
+            on_invalidate: None,
+        }
+    }
+    // 系统提示词，总是放在第一条
+    pub fn system_prompt(mut self, content: &str) -> Self {
+        self.system = Some(Message::system_message(content));
+        self
+    }
+    // 添加 tool，同名的 tool 会被替换；tools 按名称排序，与添加的顺序无关
+    pub fn tool(mut self, tool: Tool) -> Self {
+        self.tools.retain(|t| t.name() != tool.name());
+        self.tools.push(tool);
+        self.tools.sort_by(|a, b| a.name().cmp(&b.name()));
+        self
+    }
+    // 前缀失效时的回调
+    pub fn on_invalidate(mut self, callback: impl Fn(&PrefixBreak) + Send + Sync + 'static) -> Self {
+        self.on_invalidate = Some(Arc::new(callback));
+        self
+    }
+    // 修改系统提示词，会使整个缓存前缀失效
+    pub fn set_system_prompt(&mut self, content: &str) {
+        self.system = Some(Message::system_message(content));
+    }
+    // 在末尾追加消息
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+    // 修改已有的消息，会使该消息之后的缓存前缀失效
+    pub fn messages_mut(&mut self) -> &mut Vec<Message> {
+        &mut self.messages
+    }
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// 生成下一次请求。与上一次请求相比前缀被修改时调用 on_invalidate 并返回失效原因
+    pub fn request(&mut self) -> Result<(ChatRequest, Option<PrefixBreak>), DeepSeekError> {
+        let mut builder = self.builder.clone();
+        for message in self.system.iter().chain(&self.messages) {
+            builder = builder.add_message(message.clone());
+        }
+        for tool in &self.tools {
+            builder = builder.tools(tool.clone());
+        }
+        let (_, request) = builder.build();
+        let prefix = SentPrefix {
+            system: self.system.as_ref().map(serde_json::to_string).transpose()?,
+            tools: serde_json::to_string(&self.tools)?,
+            messages: self
+                .messages
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<Result<_, _>>()?,
+        };
+        let broken = self.sent.as_ref().and_then(|sent| prefix_break(sent, &prefix));
+        if let Some(broken) = &broken {
+            #[cfg(feature = "tracing")]
+            ::tracing::warn!(reason = %broken, "DeepSeek 缓存前缀失效");
+            if let Some(callback) = &self.on_invalidate {
+                callback(broken);
+            }
+        }
+        self.sent = Some(prefix);
+        Ok((request, broken))
+    }
+
+    // 记录一次响应的用量，并把回复（包括 tool 调用）追加到对话中
+    pub fn record(&mut self, response: &ChatResponse) {
+        if let Some(usage) = response.usage() {
+            self.stats.record(usage);
+        }
+        if let Some(choice) = response.choices.first() {
+            self.messages.push(choice.to_message());
+        }
+    }
+
+    /// 追加用户消息并发送，回复会追加到对话中；请求失败时对话保持不变
+    #[cfg(feature = "client")]
+    pub async fn send(&mut self, client: &DeepSeekClient, content: &str) -> Result<ChatResponse, DeepSeekError> {
+        let sent = self.sent.clone();
+        self.push(Message::user_message(content));
+        let result = match self.request() {
+            Ok((request, _)) => client.chat(&request).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(response) => {
+                self.record(&response);
+                Ok(response)
+            }
+            Err(err) => {
+                self.messages.pop();
+                self.sent = sent;
+                Err(err)
+            }
+        }
+    }
+}
+
+fn prefix_break(sent: &SentPrefix, next: &SentPrefix) -> Option<PrefixBreak> {
+    if sent.system != next.system {
+        return Some(PrefixBreak::SystemChanged);
+    }
+    if sent.tools != next.tools {
+        return Some(PrefixBreak::ToolsChanged);
+    }
+    sent.messages
+        .iter()
+        .zip(next.messages.iter().map(Some).chain(std::iter::repeat(None)))
+        .position(|(old, new)| new != Some(old))
+        .map(|index| PrefixBreak::MessageChanged { index })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_builder_messages_and_tools() {
+        let builder = ChatRequestBuilder::new()
+            .add_message(Message::user_message("旧消息"))
+            .tools(Tool::function().function_name("old"));
+        let mut conversation = Conversation::new(builder).system_prompt("系统");
+        conversation.push(Message::user_message("你好"));
+        let (request, _) = conversation.request().unwrap();
+        let contents: Vec<&str> = request.messages().iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["系统", "你好"]);
+        assert!(request.tools().is_none());
+    }
+
+    #[test]
+    fn records_tool_calls() {
+        let response: ChatResponse = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "deepseek-chat",
+            "system_fingerprint": "fp",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "tool_calls",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "weather", "arguments": "{}"}}]
+                }
+            }]
+        }))
+        .unwrap();
+        let mut conversation = Conversation::default();
+        conversation.record(&response);
+        assert_eq!(conversation.messages()[0].get_tool_calls().len(), 1);
+    }
+}
@@ -1,6 +1,12 @@
 //! chat api
+pub mod history;
 pub mod request;
 pub mod response;
+pub mod stream;
+pub mod template;
 
+pub use history::*;
 pub use request::*;
 pub use response::*;
+pub use stream::*;
+pub use template::*;
@@ -1,5 +1,10 @@
 use super::super::base_types::data::*;
+use super::response::ToolCall;
+#[cfg(feature = "client")]
+use crate::cancel::CancelToken;
+use crate::timeout::Timeouts;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// chat类型请求
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -32,7 +37,7 @@ pub struct ChatRequest {
     response_format: Option<RespinseFormat>,
     // 一个 string 或最多包含 16 个 string 的 list，在遇到这些词时，API 将停止生成更多的 token。
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<String>>,
+    stop: Option<Stop>,
     // 如果设置为 True，将会以 SSE（server-sent events）的形式以流式发送消息增量。消息流以 data: [DONE] 结尾。
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
@@ -48,6 +53,13 @@ pub struct ChatRequest {
     // 最多支持 128 个 function。
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    // 本次请求的超时，不会发送给接口
+    #[serde(skip)]
+    timeouts: Timeouts,
+    // 取消令牌，不会发送给接口
+    #[cfg(feature = "client")]
+    #[serde(skip)]
+    cancel: Option<CancelToken>,
 }
 
 impl ChatRequest {
@@ -60,6 +72,77 @@ impl ChatRequest {
     pub fn add_messages(&mut self, messages: Vec<Message>) {
         self.messages.extend(messages);
     }
+    // 是否以 SSE 的形式返回
+    pub fn set_stream(&mut self, stream: bool) {
+        self.stream = Some(stream);
+    }
+    // 修改模型 id，例如换成 provider 上的模型名称
+    pub fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+    }
+    pub fn set_response_format(&mut self, response_format: RespinseFormat) {
+        self.response_format = Some(response_format);
+    }
+    // 由已有请求生成构建器，便于只修改部分参数后重新构建。
+    // 本库不认识的模型名称保留为 ModelName::Custom。
+    pub fn to_builder(&self) -> ChatRequestBuilder {
+        ChatRequestBuilder {
+            messages: self.messages.clone(),
+            model: ModelName::from_id(&self.model),
+            frequency_penalty: self.frequency_penalty,
+            max_tokens: self.max_tokens,
+            presence_penalty: self.presence_penalty,
+            response_format: self.response_format.clone(),
+            stop: self.stop.clone(),
+            stream: self.stream,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            tools: self.tools.clone(),
+            timeouts: self.timeouts,
+            #[cfg(feature = "client")]
+            cancel: self.cancel.clone(),
+        }
+    }
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn frequency_penalty(&self) -> Option<f64> {
+        self.frequency_penalty
+    }
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+    pub fn presence_penalty(&self) -> Option<f64> {
+        self.presence_penalty
+    }
+    pub fn response_format(&self) -> Option<&RespinseFormat> {
+        self.response_format.as_ref()
+    }
+    pub fn stop(&self) -> Option<&Stop> {
+        self.stop.as_ref()
+    }
+    pub fn stream(&self) -> Option<bool> {
+        self.stream
+    }
+    pub fn temperature(&self) -> Option<f64> {
+        self.temperature
+    }
+    pub fn top_p(&self) -> Option<f64> {
+        self.top_p
+    }
+    pub fn tools(&self) -> Option<&[Tool]> {
+        self.tools.as_deref()
+    }
+    pub fn get_timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+    #[cfg(feature = "client")]
+    pub fn get_cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
 }
 
 /// chat类型请求构建器
@@ -93,7 +176,7 @@ pub struct ChatRequestBuilder{
     response_format: Option<RespinseFormat>,
     // 一个 string 或最多包含 16 个 string 的 list，在遇到这些词时，API 将停止生成更多的 token。
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<String>>,
+    stop: Option<Stop>,
     // 如果设置为 True，将会以 SSE（server-sent events）的形式以流式发送消息增量。消息流以 data: [DONE] 结尾。
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
@@ -109,6 +192,19 @@ pub struct ChatRequestBuilder{
     // 最多支持 128 个 function。
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    // 本次请求的超时，不会发送给接口
+    #[serde(skip)]
+    timeouts: Timeouts,
+    // 取消令牌，不会发送给接口
+    #[cfg(feature = "client")]
+    #[serde(skip)]
+    cancel: Option<CancelToken>,
+}
+
+impl Default for ChatRequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ChatRequestBuilder {
@@ -124,7 +220,10 @@ impl ChatRequestBuilder {
             stream: None,
             temperature: None,
             top_p: None,
-            tools: None
+            tools: None,
+            timeouts: Timeouts::default(),
+            #[cfg(feature = "client")]
+            cancel: None,
         }
     }
     pub fn add_message(mut self, message: Message) -> Self {
@@ -152,10 +251,19 @@ impl ChatRequestBuilder {
         self.response_format = Some(response_format);
         self
     }
-    pub fn stop(mut self, stop: Vec<String>) -> Self {
-        self.stop = Some(stop);
+    // 设置停止词，单个字符串可以直接传入，列表用 `Stop::try_from(vec)?` 检查数量。
+    pub fn stop(mut self, stop: impl Into<Stop>) -> Self {
+        self.stop = Some(stop.into());
         self
     }
+    // 追加一个停止词，超过 16 个时返回错误。
+    pub fn add_stop(mut self, stop: &str) -> Result<Self, StopError> {
+        self.stop = Some(match self.stop.take() {
+            Some(exist) => exist.push(stop)?,
+            None => Stop::from(stop),
+        });
+        Ok(self)
+    }
     pub fn stream(mut self, stream: bool) -> Self {
         self.stream = Some(stream);
         self
@@ -172,14 +280,26 @@ impl ChatRequestBuilder {
         self.tools.get_or_insert_with(Vec::new).push(tools);
         self
     }
+    // 去掉消息和 tools，只保留模型、采样参数等设置
+    pub(crate) fn without_messages(mut self) -> Self {
+        self.messages.clear();
+        self.tools = None;
+        self
+    }
+    // 覆盖客户端上设置的超时，见 [`crate::timeout`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    #[cfg(feature = "client")]
+    // 取消令牌，见 [`crate::cancel`]
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
     pub fn build(self) -> (String, ChatRequest) {
-        let mut base_url = String::new();
-        if self.model == ModelName::DeepseekChat{
-            base_url = format!("{}https://api.deepseek.com/chat",base_url);
-        }else if self.model == ModelName::DeepseekReasoner{
-            base_url = format!("{}https://api.deepseek.com/chat",base_url);
-        }
-        base_url = format!("{}/completions",base_url);
+        // deepseek-chat 与 deepseek-reasoner 使用同一个接口
+        let base_url = String::from("https://api.deepseek.com/chat/completions");
         
         (
             base_url,
@@ -195,6 +315,9 @@ impl ChatRequestBuilder {
                 temperature: self.temperature,
                 top_p: self.top_p,
                 tools: self.tools,
+                timeouts: self.timeouts,
+                #[cfg(feature = "client")]
+                cancel: self.cancel,
             }
         )
     }
@@ -219,6 +342,9 @@ pub struct Message{
     // 此消息所响应的 tool call 的 ID。
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_call_id: Option<String>,
+    // role 为 assistant 时，模型在这条消息中发起的 tool 调用。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
 }
 
 impl Message {
@@ -230,6 +356,7 @@ impl Message {
             prefix: None,
             reasoning_content: None,
             tool_call_id: None,
+            tool_calls: None,
         }
     }
     pub fn user_message(content: &str) -> Self {
@@ -240,6 +367,7 @@ impl Message {
             prefix: None,
             reasoning_content: None,
             tool_call_id: None,
+            tool_calls: None,
         }
     }
     pub fn assistant_message(content: &str) -> Self {
@@ -250,8 +378,39 @@ impl Message {
             prefix: None,
             reasoning_content: None,
             tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+    // tool 的执行结果，tool_call_id 为对应的 tool 调用的 ID
+    pub fn tool_message(tool_call_id: &str, content: &str) -> Self {
+        Message {
+            content: String::from(content),
+            role: String::from("tool"),
+            name: None,
+            prefix: None,
+            reasoning_content: None,
+            tool_call_id: Some(String::from(tool_call_id)),
+            tool_calls: None,
         }
     }
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+    pub fn get_tool_call_id(&self) -> Option<&str> {
+        self.tool_call_id.as_deref()
+    }
+    pub fn get_tool_calls(&self) -> &[ToolCall] {
+        self.tool_calls.as_deref().unwrap_or_default()
+    }
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub(crate) fn set_content(&mut self, content: String) {
+        self.content = content;
+    }
     // 可以选填的参与者的名称，为模型提供信息以区分相同角色的参与者。
     pub fn name(mut self, name: &str) -> Self {
         if self.role == "system" || self.role == "user" || self.role == "assistant"{
@@ -280,9 +439,127 @@ impl Message {
         }
         self
     }
+    // role 类型为 assistant 时，模型在这条消息中发起的 tool 调用，用于把 tool 调用放回对话历史。
+    pub fn tool_calls(mut self, calls: Vec<ToolCall>) -> Self {
+        if self.role == "assistant" && !calls.is_empty() {
+            self.tool_calls = Some(calls);
+        }
+        self
+    }
     
 }
 
+/// 停止词最多 16 个
+pub const MAX_STOP_SEQUENCES: usize = 16;
+
+/// 停止词，可以是单个 string 或包含 1 到 16 个 string 的 list。
+/// 空 list 或超过 16 个时返回 [`StopError`]，反序列化（例如批量任务的输入）时同样会检查。
+/// DeepSeek 不会在回复中返回触发的停止词，所以无法判断是哪个停止词结束了生成
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "StopRepr", into = "StopRepr")]
+pub struct Stop(StopRepr);
+
+// 接口接受的两种格式
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+enum StopRepr {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+/// 停止词列表为空或超过 [`MAX_STOP_SEQUENCES`] 个
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopError {
+    pub count: usize,
+}
+
+impl fmt::Display for StopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "停止词列表不能为空");
+        }
+        write!(f, "停止词最多 {} 个，实际为 {} 个", MAX_STOP_SEQUENCES, self.count)
+    }
+}
+
+impl std::error::Error for StopError {}
+
+impl Stop {
+    // 追加一个停止词，超过 16 个时返回错误
+    pub fn push(self, stop: &str) -> Result<Self, StopError> {
+        let mut sequences = self.into_vec();
+        sequences.push(String::from(stop));
+        Stop::try_from(sequences)
+    }
+    // 所有停止词
+    pub fn sequences(&self) -> Vec<&str> {
+        match &self.0 {
+            StopRepr::Single(stop) => vec![stop.as_str()],
+            StopRepr::Multiple(stops) => stops.iter().map(|s| s.as_str()).collect(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            StopRepr::Single(_) => 1,
+            StopRepr::Multiple(stops) => stops.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn into_vec(self) -> Vec<String> {
+        match self.0 {
+            StopRepr::Single(stop) => vec![stop],
+            StopRepr::Multiple(stops) => stops,
+        }
+    }
+}
+
+impl From<&str> for Stop {
+    fn from(stop: &str) -> Self {
+        Stop(StopRepr::Single(String::from(stop)))
+    }
+}
+
+impl From<String> for Stop {
+    fn from(stop: String) -> Self {
+        Stop(StopRepr::Single(stop))
+    }
+}
+
+impl TryFrom<Vec<String>> for Stop {
+    type Error = StopError;
+    fn try_from(stops: Vec<String>) -> Result<Self, Self::Error> {
+        if stops.is_empty() || stops.len() > MAX_STOP_SEQUENCES {
+            return Err(StopError { count: stops.len() });
+        }
+        Ok(Stop(StopRepr::Multiple(stops)))
+    }
+}
+
+impl TryFrom<Vec<&str>> for Stop {
+    type Error = StopError;
+    fn try_from(stops: Vec<&str>) -> Result<Self, Self::Error> {
+        Stop::try_from(stops.into_iter().map(String::from).collect::<Vec<String>>())
+    }
+}
+
+impl TryFrom<StopRepr> for Stop {
+    type Error = StopError;
+    fn try_from(repr: StopRepr) -> Result<Self, Self::Error> {
+        match repr {
+            StopRepr::Single(stop) => Ok(Stop::from(stop)),
+            StopRepr::Multiple(stops) => Stop::try_from(stops),
+        }
+    }
+}
+
+impl From<Stop> for StopRepr {
+    fn from(stop: Stop) -> Self {
+        stop.0
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RespinseFormat{
     // ai回复的格式，可选值为 `text` 和 `json_object`。
@@ -291,6 +568,9 @@ pub struct RespinseFormat{
 }
 
 impl RespinseFormat {
+    pub fn format_type(&self) -> &str {
+        &self.format_type
+    }
     // 设置ai回复为文本格式
     pub fn text() -> Self {
         RespinseFormat {
@@ -339,6 +619,10 @@ impl Tool {
         }
         self
     }
+    // function 的名称
+    pub fn name(&self) -> Option<&str> {
+        self.function.as_ref().map(|f| f.name.as_str())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -352,6 +636,12 @@ pub struct Function{
     parameters: serde_json::Value,
 }
 
+impl Default for Function {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Function {
     pub fn new() -> Self {
         Function {
@@ -366,7 +656,46 @@ impl Function {
     pub fn name(&mut self, name: &str) {
         self.name = name.to_string();
     }
+    // 参数中对象的键会按字典序排列，保证序列化结果稳定，便于命中前缀缓存
     pub fn parameters(&mut self, parameters: serde_json::Value) {
-        self.parameters = parameters;
+        self.parameters = super::history::canonicalize(parameters);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_serializes_single_and_list() {
+        let (_, request) = ChatRequestBuilder::new().stop("END").build();
+        assert_eq!(serde_json::to_value(&request).unwrap()["stop"], serde_json::json!("END"));
+        let stop = Stop::try_from(vec!["a", "b"]).unwrap();
+        let (_, request) = ChatRequestBuilder::new().stop(stop).build();
+        assert_eq!(serde_json::to_value(&request).unwrap()["stop"], serde_json::json!(["a", "b"]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn stop_rejects_empty_and_more_than_16() {
+        assert_eq!(Stop::try_from(Vec::<String>::new()), Err(StopError { count: 0 }));
+        let max: Vec<String> = (0..MAX_STOP_SEQUENCES).map(|i| i.to_string()).collect();
+        assert_eq!(Stop::try_from(max.clone()).unwrap().len(), MAX_STOP_SEQUENCES);
+        let mut over = max;
+        over.push(String::from("16"));
+        assert!(serde_json::from_value::<Stop>(serde_json::json!(over)).is_err());
+        assert_eq!(Stop::try_from(over), Err(StopError { count: 17 }));
+        assert!(serde_json::from_value::<Stop>(serde_json::json!([])).is_err());
+    }
+
+    #[test]
+    fn add_stop_appends_until_limit() {
+        let mut builder = ChatRequestBuilder::new().add_stop("a").unwrap();
+        let (_, request) = builder.clone().build();
+        assert_eq!(serde_json::to_value(&request).unwrap()["stop"], serde_json::json!("a"));
+        for i in 1..MAX_STOP_SEQUENCES {
+            builder = builder.add_stop(&i.to_string()).unwrap();
+        }
+        let (_, request) = builder.clone().build();
+        assert_eq!(serde_json::to_value(&request).unwrap()["stop"].as_array().unwrap().len(), MAX_STOP_SEQUENCES);
+        assert_eq!(builder.add_stop("over").unwrap_err(), StopError { count: 17 });
+    }
+}
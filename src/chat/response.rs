@@ -1,24 +1,55 @@
+use super::request::Message;
+use crate::base_types::data::Pricing;
+use crate::lenient::{null_default, skip_extra, Extra};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// 请求被取消时 [`ChatAccumulator`](super::ChatAccumulator) 为没有结束的 choice 设置的 finish_reason，不是接口返回的值
+pub const FINISH_REASON_CANCELLED: &str = "cancelled";
+
+/// chat 响应。缺少或为 null 的字段使用缺省值，未知字段保存在 [`ChatResponse::extra`] 中，见 [`crate::lenient`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ChatResponse {
     // 该对话的唯一标识符。
+    #[serde(deserialize_with = "null_default")]
     id: String,
     // 模型生成的 completion 的选择列表。
+    #[serde(deserialize_with = "null_default")]
     pub choices: Vec<Choice>,
     // 创建聊天完成时的 Unix 时间戳（以秒为单位）。
+    #[serde(deserialize_with = "null_default")]
     created: isize,
     // 生成该 completion 的模型名。
+    #[serde(deserialize_with = "null_default")]
     model: String,
     // This fingerprint represents the backend configuration that the model runs with
-    system_fingerprint: String,
+    system_fingerprint: Option<String>,
     // 对象的类型, 其值为 chat.completion。
+    #[serde(deserialize_with = "null_default")]
     object: String,
     // 该对话补全请求的用量信息。
     usage: Option<Usage>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
 impl ChatResponse {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+    pub fn system_fingerprint(&self) -> Option<&str> {
+        self.system_fingerprint.as_deref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
     pub fn content(&self) -> Vec<&str> {
         self.choices.iter().map(|c| c.content()).collect()
     }
@@ -27,33 +58,73 @@ impl ChatResponse {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct Choice {
     // 模型停止生成 token 的原因。
     // stop：模型自然停止生成，或遇到 stop 序列中列出的字符串。
     // length ：输出长度达到了模型上下文长度限制，或达到了 max_tokens 的限制。
     // content_filter：输出内容因触发过滤策略而被过滤。
     // insufficient_system_resource：系统推理资源不足，生成被打断。
-    finish_reason: String,
+    // cancelled：请求被取消，只由客户端设置，见 FINISH_REASON_CANCELLED。
+    // 缺少或为 null 时为 None。
+    finish_reason: Option<String>,
     // 该 completion 在模型生成的 completion 的选择列表中的索引。
+    #[serde(deserialize_with = "null_default")]
     index: usize,
     // 模型生成的 completion 消息。
-    #[serde(rename = "message")]
+    #[serde(rename = "message", deserialize_with = "null_default")]
     response_content: ResponseMessage,
     // 该 choice 的对数概率信息。
     logprobs: Option<Logprobs>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
 impl Choice {
     pub fn content(&self) -> &str {
         self.response_content.cotent()
     }
+    // 回复内容，接口没有返回 content 时为 None
+    pub fn get_content(&self) -> Option<&str> {
+        self.response_content.content.as_deref()
+    }
     pub fn role(&self) -> &str {
         self.response_content.role()
     }
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn reasoning_content(&self) -> Option<&str> {
+        self.response_content.reasoning_content.as_deref()
+    }
+    pub fn logprobs(&self) -> Option<&Logprobs> {
+        self.logprobs.as_ref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 模型发起的 tool 调用
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.response_content.tool_calls.as_deref().unwrap_or_default()
+    }
+    // 转换为可以放回对话历史的 assistant 消息，包括 tool 调用，不包括推理内容
+    pub fn to_message(&self) -> Message {
+        Message::assistant_message(self.response_content.content.as_deref().unwrap_or_default())
+            .tool_calls(self.tool_calls().to_vec())
+    }
+    // 是否因为请求被取消而没有生成完
+    pub fn is_cancelled(&self) -> bool {
+        self.finish_reason() == Some(FINISH_REASON_CANCELLED)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ResponseMessage {
     // 该 completion 的内容。
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -63,7 +134,11 @@ pub struct ResponseMessage {
     // 模型生成的 tool 调用，例如 function 调用。
     tool_calls: Option<Vec<ToolCall>>,
     // 生成这条消息的角色。
+    #[serde(deserialize_with = "null_default")]
     role: String,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
 impl ResponseMessage {
@@ -74,76 +149,286 @@ impl ResponseMessage {
     pub fn role(&self) -> &str {
         self.role.as_str()
     }
+
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ToolCall {
     // tool 调用的 ID。
+    #[serde(deserialize_with = "null_default")]
     id: String,
     // tool 的类型。目前仅支持 function。
-    #[serde(rename = "type")]
+    #[serde(rename = "type", deserialize_with = "null_default")]
     type_name: String,
-    #[serde(rename = "function")]
+    #[serde(rename = "function", deserialize_with = "null_default")]
     response_function: ResponseFunction,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl ToolCall {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+    // 调用的 function 名称
+    pub fn name(&self) -> &str {
+        &self.response_function.name
+    }
+    // 参数的 JSON 字符串，由模型生成，可能不是有效的 JSON
+    pub fn arguments(&self) -> &str {
+        &self.response_function.arguments
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 把参数解析为 T
+    pub fn parse_arguments<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.response_function.arguments)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ResponseFunction {
     // 模型调用的 function。
+    #[serde(deserialize_with = "null_default")]
     name: String,
     // 要调用的 function 的参数，由模型生成，格式为 JSON。请注意，模型并不总是生成有效的 JSON，
     // 并且可能会臆造出你函数模式中未定义的参数。在调用函数之前，请在代码中验证这些参数。
+    #[serde(deserialize_with = "null_default")]
     arguments: String,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct Logprobs {
     // 一个包含输出 token 对数概率信息的列表。
-    content: LogprobsContent,
+    #[serde(deserialize_with = "null_default")]
+    content: Vec<LogprobsContent>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl Logprobs {
+    pub fn content(&self) -> &[LogprobsContent] {
+        &self.content
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct LogprobsContent {
     // 输出的 token。
-    tokens: String,
+    #[serde(deserialize_with = "null_default")]
+    token: String,
     // 该 token 的对数概率。-9999.0 代表该 token 的输出概率极小，不在 top 20 最可能输出的 token 中。
-    logprob: isize,
+    #[serde(deserialize_with = "null_default")]
+    logprob: f64,
     // 一个包含该 token UTF-8 字节表示的整数列表。一般在一个 UTF-8 字符被拆分成多个 token 来表示时有用。如果 token 没有对应的字节表示，则该值为 null。
-    bytes: Option<isize>,
+    bytes: Option<Vec<u8>>,
     // 一个包含在该输出位置上，输出概率 top N 的 token 的列表，以及它们的对数概率。
     // 在罕见情况下，返回的 token 数量可能少于请求参数中指定的 top_logprobs 值。
-    top_logprobs: TopLogprobs,
+    #[serde(deserialize_with = "null_default")]
+    top_logprobs: Vec<TopLogprobs>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl LogprobsContent {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+    pub fn logprob(&self) -> f64 {
+        self.logprob
+    }
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
+    pub fn top_logprobs(&self) -> &[TopLogprobs] {
+        &self.top_logprobs
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct TopLogprobs {
     // token。
+    #[serde(deserialize_with = "null_default")]
     token: String,
     // 该 token 的对数概率。
-    logprob: isize,
+    #[serde(deserialize_with = "null_default")]
+    logprob: f64,
     // 一个包含该 token UTF-8 字节表示的整数列表。一般在一个 UTF-8 字符被拆分成多个 token 来表示时有用。
     // 如果 token 没有对应的字节表示，则该值为 null。
-    bytes: Option<isize>,
+    bytes: Option<Vec<u8>>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl TopLogprobs {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+    pub fn logprob(&self) -> f64 {
+        self.logprob
+    }
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct Usage {
     // 模型 completion 产生的 token 数。
+    #[serde(deserialize_with = "null_default")]
     completion_tokens: isize,
     // 用户 prompt 所包含的 token 数。该值等于 prompt_cache_hit_tokens + prompt_cache_miss_tokens
+    #[serde(deserialize_with = "null_default")]
     prompt_tokens: isize,
-    // 用户 prompt 中，命中上下文缓存的 token 数。
-    prompt_cache_hit_tokens: isize,
-    // 用户 prompt 中，未命中上下文缓存的 token 数。
-    prompt_cache_miss_tokens: isize,
+    // 用户 prompt 中，命中上下文缓存的 token 数。其他 OpenAI 兼容服务可能没有这一项。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_cache_hit_tokens: Option<isize>,
+    // 用户 prompt 中，未命中上下文缓存的 token 数。其他 OpenAI 兼容服务可能没有这一项。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_cache_miss_tokens: Option<isize>,
     // 请求的 token 数量。
+    #[serde(deserialize_with = "null_default")]
     total_tokens: usize,
     // 该请求中，所有 token 的数量（prompt + completion）
-    prompt_tokens_details: PormptTokensDetails,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_tokens_details: Option<PormptTokensDetails>,
+    // 本库没有定义的字段，例如 completion_tokens_details
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl Usage {
+    pub fn completion_tokens(&self) -> isize {
+        self.completion_tokens
+    }
+    pub fn prompt_tokens(&self) -> isize {
+        self.prompt_tokens
+    }
+    // 没有缓存信息时使用 OpenAI 格式的 prompt_tokens_details.cached_tokens，都没有时为 0
+    pub fn prompt_cache_hit_tokens(&self) -> isize {
+        self.prompt_cache_hit_tokens
+            .or_else(|| self.prompt_tokens_details.as_ref().and_then(|d| d.cached_tokens))
+            .unwrap_or(0)
+    }
+    // 没有缓存信息时为 prompt_tokens 中没有命中缓存的部分
+    pub fn prompt_cache_miss_tokens(&self) -> isize {
+        self.prompt_cache_miss_tokens
+            .unwrap_or_else(|| self.prompt_tokens - self.prompt_cache_hit_tokens())
+    }
+    // 响应中是否包含缓存命中信息
+    pub fn has_cache_info(&self) -> bool {
+        self.prompt_cache_hit_tokens.is_some()
+            || self.prompt_tokens_details.as_ref().is_some_and(|d| d.cached_tokens.is_some())
+    }
+    pub fn total_tokens(&self) -> usize {
+        self.total_tokens
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 按价格计算本次请求的费用
+    pub fn cost(&self, pricing: &Pricing) -> f64 {
+        pricing.cost(
+            self.prompt_cache_hit_tokens().max(0) as usize,
+            self.prompt_cache_miss_tokens().max(0) as usize,
+            self.completion_tokens.max(0) as usize,
+        )
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct PormptTokensDetails {
     // 推理模型所产生的思维链 token 数量
-    cached_tokens: isize,
-}
\ No newline at end of file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cached_tokens: Option<isize>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(value: serde_json::Value) -> ChatResponse {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn missing_optional_fields_use_defaults() {
+        // 没有 system_fingerprint 和 prompt_tokens_details
+        let response = response(json!({
+            "id": "1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "deepseek-chat",
+            "choices": [{"index": 0, "finish_reason": "stop", "message": {"role": "assistant", "content": "你好"}}],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12}
+        }));
+        assert_eq!(response.system_fingerprint(), None);
+        let usage = response.usage().unwrap();
+        assert!(!usage.has_cache_info());
+        assert_eq!(usage.prompt_cache_hit_tokens(), 0);
+        assert_eq!(usage.prompt_cache_miss_tokens(), 10);
+    }
+
+    #[test]
+    fn null_finish_reason_is_none() {
+        let response = response(json!({
+            "id": "1",
+            "choices": [{"index": 0, "finish_reason": null, "message": {"role": "assistant", "content": null}}],
+            "usage": null
+        }));
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason(), None);
+        assert_eq!(choice.get_content(), None);
+        assert!(response.usage().is_none());
+    }
+
+    #[test]
+    fn unknown_fields_are_kept_in_extra() {
+        let value = json!({
+            "id": "1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "deepseek-chat",
+            "service_tier": "default",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "score": 0.5,
+                "message": {"role": "assistant", "content": "你好", "refusal": null}
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2, "completion_tokens_details": {"reasoning_tokens": 0}}
+        });
+        let response = response(value.clone());
+        assert_eq!(response.extra()["service_tier"], "default");
+        assert_eq!(response.choices[0].extra()["score"], 0.5);
+        assert_eq!(response.usage().unwrap().extra()["completion_tokens_details"]["reasoning_tokens"], 0);
+        // 序列化时原样输出
+        let output = serde_json::to_value(&response).unwrap();
+        assert_eq!(output["service_tier"], value["service_tier"]);
+        assert_eq!(output["choices"][0]["message"]["refusal"], json!(null));
+        assert!(output["choices"][0]["message"].as_object().unwrap().contains_key("refusal"));
+    }
+}
@@ -0,0 +1,413 @@
+//! # 流式响应
+//! 请求中设置 `stream: true` 时，接口以 SSE 的形式返回 `chat.completion.chunk`，以 `data: [DONE]` 结尾。
+use super::response::{ChatResponse, Usage, FINISH_REASON_CANCELLED};
+use crate::error::DeepSeekError;
+#[cfg(feature = "client")]
+use crate::http::ByteStream;
+use crate::lenient::{null_default, skip_extra, Extra};
+#[cfg(feature = "client")]
+use crate::lenient::Drift;
+#[cfg(feature = "client")]
+use crate::sse::JsonEventDecoder;
+#[cfg(feature = "client")]
+use futures::stream::BoxStream;
+#[cfg(feature = "client")]
+use futures::StreamExt;
+#[cfg(feature = "client")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// chat 流式响应
+#[cfg(feature = "client")]
+pub type ChatStream = BoxStream<'static, Result<ChatCompletionChunk, DeepSeekError>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ChatCompletionChunk {
+    // 该对话的唯一标识符，每个 chunk 相同。
+    #[serde(deserialize_with = "null_default")]
+    id: String,
+    // 模型生成的 completion 的增量列表。
+    #[serde(deserialize_with = "null_default")]
+    choices: Vec<ChunkChoice>,
+    // 创建聊天完成时的 Unix 时间戳（以秒为单位）。
+    #[serde(deserialize_with = "null_default")]
+    created: isize,
+    // 生成该 completion 的模型名。
+    #[serde(deserialize_with = "null_default")]
+    model: String,
+    // This fingerprint represents the backend configuration that the model runs with
+    system_fingerprint: Option<String>,
+    // 对象的类型, 其值为 chat.completion.chunk。
+    #[serde(deserialize_with = "null_default")]
+    object: String,
+    // 用量信息，只在最后一个 chunk 中出现。
+    usage: Option<Usage>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl ChatCompletionChunk {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn choices(&self) -> &[ChunkChoice] {
+        &self.choices
+    }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 是否包含模型输出（内容、推理内容或 tool 调用）
+    pub fn has_output(&self) -> bool {
+        self.choices.iter().any(|c| {
+            c.delta.content().is_some_and(|s| !s.is_empty())
+                || c.delta.reasoning_content().is_some_and(|s| !s.is_empty())
+                || !c.delta.tool_calls().is_empty()
+        })
+    }
+    // 每个 choice 本次新增的内容
+    pub fn content(&self) -> Vec<&str> {
+        self.choices.iter().map(|c| c.delta.content().unwrap_or_default()).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ChunkChoice {
+    // 该 choice 在列表中的索引。
+    #[serde(deserialize_with = "null_default")]
+    index: usize,
+    // 本次新增的内容。
+    #[serde(deserialize_with = "null_default")]
+    delta: Delta,
+    // 模型停止生成 token 的原因，只在该 choice 的最后一个 chunk 中出现。
+    finish_reason: Option<String>,
+    // 本库没有定义的字段，例如 logprobs
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl ChunkChoice {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn delta(&self) -> &Delta {
+        &self.delta
+    }
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Delta {
+    // 生成这条消息的角色，只在第一个 chunk 中出现。
+    #[serde(default)]
+    role: Option<String>,
+    // 新增的内容。
+    #[serde(default)]
+    content: Option<String>,
+    // 仅适用于 deepseek-reasoner 模型，新增的推理内容。
+    #[serde(default)]
+    reasoning_content: Option<String>,
+    // 新增的 tool 调用片段。
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCallDelta>>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl Delta {
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+    pub fn reasoning_content(&self) -> Option<&str> {
+        self.reasoning_content.as_deref()
+    }
+    pub fn tool_calls(&self) -> &[ToolCallDelta] {
+        self.tool_calls.as_deref().unwrap_or_default()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolCallDelta {
+    // 该 tool 调用在列表中的索引，同一个调用的片段索引相同。
+    #[serde(default, deserialize_with = "null_default")]
+    index: usize,
+    // tool 调用的 ID，只在第一个片段中出现。
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(rename = "type", default)]
+    type_name: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl ToolCallDelta {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    pub fn name(&self) -> Option<&str> {
+        self.function.as_ref().and_then(|f| f.name.as_deref())
+    }
+    pub fn arguments(&self) -> Option<&str> {
+        self.function.as_ref().and_then(|f| f.arguments.as_deref())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+/// 把流式响应的 chunk 合并为完整的响应。请求被取消时，已经收到的部分仍然可以生成响应，
+/// 没有结束的 choice 的 finish_reason 为 [`FINISH_REASON_CANCELLED`]
+#[derive(Debug, Clone, Default)]
+pub struct ChatAccumulator {
+    id: String,
+    created: isize,
+    model: String,
+    system_fingerprint: Option<String>,
+    usage: Option<Usage>,
+    choices: Vec<PartialChoice>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PartialChoice {
+    role: Option<String>,
+    content: String,
+    reasoning_content: String,
+    tool_calls: Vec<PartialToolCall>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PartialToolCall {
+    id: String,
+    type_name: String,
+    name: String,
+    arguments: String,
+}
+
+impl ChatAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, chunk: &ChatCompletionChunk) {
+        self.id.clone_from(&chunk.id);
+        self.created = chunk.created;
+        self.model.clone_from(&chunk.model);
+        if chunk.system_fingerprint.is_some() {
+            self.system_fingerprint.clone_from(&chunk.system_fingerprint);
+        }
+        if chunk.usage.is_some() {
+            self.usage.clone_from(&chunk.usage);
+        }
+        for choice in &chunk.choices {
+            if self.choices.len() <= choice.index {
+                self.choices.resize_with(choice.index + 1, PartialChoice::default);
+            }
+            let partial = &mut self.choices[choice.index];
+            let delta = &choice.delta;
+            if let Some(role) = delta.role() {
+                partial.role = Some(role.to_string());
+            }
+            partial.content.push_str(delta.content().unwrap_or_default());
+            partial.reasoning_content.push_str(delta.reasoning_content().unwrap_or_default());
+            for call in delta.tool_calls() {
+                if partial.tool_calls.len() <= call.index {
+                    partial.tool_calls.resize_with(call.index + 1, PartialToolCall::default);
+                }
+                let tool = &mut partial.tool_calls[call.index];
+                tool.id.push_str(call.id().unwrap_or_default());
+                tool.type_name.push_str(call.type_name.as_deref().unwrap_or_default());
+                tool.name.push_str(call.name().unwrap_or_default());
+                tool.arguments.push_str(call.arguments().unwrap_or_default());
+            }
+            if choice.finish_reason.is_some() {
+                partial.finish_reason.clone_from(&choice.finish_reason);
+            }
+        }
+    }
+    // 目前为止每个 choice 的内容
+    pub fn content(&self) -> Vec<&str> {
+        self.choices.iter().map(|c| c.content.as_str()).collect()
+    }
+    // 是否收到过 chunk
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+    // 所有 choice 是否都已结束
+    pub fn is_finished(&self) -> bool {
+        !self.choices.is_empty() && self.choices.iter().all(|c| c.finish_reason.is_some())
+    }
+    /// 生成完整的响应，没有结束的 choice 的 finish_reason 为 [`FINISH_REASON_CANCELLED`]
+    pub fn into_response(self) -> Result<ChatResponse, DeepSeekError> {
+        let choices: Vec<serde_json::Value> = self
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, choice)| {
+                let tool_calls: Vec<serde_json::Value> = choice
+                    .tool_calls
+                    .into_iter()
+                    .map(|call| {
+                        serde_json::json!({
+                            "id": call.id,
+                            "type": if call.type_name.is_empty() { String::from("function") } else { call.type_name },
+                            "function": { "name": call.name, "arguments": call.arguments },
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "index": index,
+                    "message": {
+                        "role": choice.role.unwrap_or_else(|| String::from("assistant")),
+                        "content": choice.content,
+                        "reasoning_content": Some(choice.reasoning_content).filter(|s| !s.is_empty()),
+                        "tool_calls": Some(tool_calls).filter(|calls| !calls.is_empty()),
+                    },
+                    "finish_reason": choice.finish_reason.unwrap_or_else(|| String::from(FINISH_REASON_CANCELLED)),
+                    "logprobs": null,
+                })
+            })
+            .collect();
+        Ok(serde_json::from_value(serde_json::json!({
+            "id": self.id,
+            "choices": choices,
+            "created": self.created,
+            "model": self.model,
+            "system_fingerprint": self.system_fingerprint,
+            "object": "chat.completion",
+            "usage": self.usage,
+        }))?)
+    }
+    #[cfg(feature = "client")]
+    /// 读取整个流并生成响应。被取消时返回已经收到的部分（一个 chunk 都没有收到时返回 `Cancelled` 错误），其他错误原样返回
+    pub async fn collect(mut stream: ChatStream) -> Result<ChatResponse, DeepSeekError> {
+        let mut accumulator = ChatAccumulator::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => accumulator.push(&chunk),
+                Err(DeepSeekError::Cancelled) if !accumulator.is_empty() => break,
+                Err(err) => return Err(err),
+            }
+        }
+        // 先关闭连接再生成响应
+        drop(stream);
+        accumulator.into_response()
+    }
+}
+
+/// 把完整的响应拆成 chunk，用于把缓存或录制的响应作为流返回：每个 choice 一个包含全部内容的 chunk，用量放在最后一个 chunk 中
+pub fn replay_chunks(response: &ChatResponse) -> Result<Vec<ChatCompletionChunk>, DeepSeekError> {
+    let value = serde_json::to_value(response)?;
+    let choices = value["choices"].as_array().cloned().unwrap_or_default();
+    let count = choices.len();
+    choices
+        .into_iter()
+        .enumerate()
+        .map(|(i, choice)| {
+            let message = &choice["message"];
+            let tool_calls: Option<Vec<serde_json::Value>> = message["tool_calls"].as_array().map(|calls| {
+                calls
+                    .iter()
+                    .enumerate()
+                    .map(|(index, call)| {
+                        let mut call = call.clone();
+                        call["index"] = index.into();
+                        call
+                    })
+                    .collect()
+            });
+            let chunk = serde_json::json!({
+                "id": value["id"],
+                "choices": [{
+                    "index": choice["index"],
+                    "delta": {
+                        "role": message["role"],
+                        "content": message["content"],
+                        "reasoning_content": message["reasoning_content"],
+                        "tool_calls": tool_calls,
+                    },
+                    "finish_reason": choice["finish_reason"],
+                }],
+                "created": value["created"],
+                "model": value["model"],
+                "system_fingerprint": value["system_fingerprint"],
+                "object": "chat.completion.chunk",
+                "usage": if i + 1 == count { value["usage"].clone() } else { serde_json::Value::Null },
+            });
+            Ok(serde_json::from_value(chunk)?)
+        })
+        .collect()
+}
+
+#[cfg(feature = "client")]
+// 把 SSE 字节流解析为 chunk 流，没有收到 [DONE] 就结束时返回错误
+pub(crate) fn chunk_stream(body: ByteStream, on_drift: Option<OnDrift>) -> ChatStream {
+    json_stream(body, on_drift)
+}
+
+#[cfg(feature = "client")]
+// 严格模式下对每个事件中不一致的地方调用
+pub(crate) type OnDrift = Box<dyn Fn(&Drift) + Send + Sync>;
+
+#[cfg(feature = "client")]
+// 把 SSE 字节流中每个事件的 data 解析为 T，设置了 on_drift 时按严格模式检查
+pub(crate) fn json_stream<T: DeserializeOwned + Serialize + Send + 'static>(
+    body: ByteStream,
+    on_drift: Option<OnDrift>,
+) -> BoxStream<'static, Result<T, DeepSeekError>> {
+    async_stream::try_stream! {
+        let mut body = body;
+        let mut decoder = match on_drift {
+            Some(_) => JsonEventDecoder::<T>::strict(),
+            None => JsonEventDecoder::<T>::new(),
+        };
+        while let Some(bytes) = body.next().await {
+            let bytes = bytes?;
+            let items = decoder.feed(&bytes);
+            if let Some(on_drift) = &on_drift {
+                decoder.take_drift().iter().for_each(on_drift);
+            }
+            for item in items {
+                yield item?;
+            }
+            if decoder.is_done() {
+                break;
+            }
+        }
+        decoder.finish()?;
+    }
+    .boxed()
+}
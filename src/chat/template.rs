@@ -0,0 +1,108 @@
+//! # 请求模板
+//! 保存可复用的请求设置（模型、系统提示词、工具、采样参数），
+//! 再把 `{{变量}}` 渲染进消息内容，为每一组输入生成一个请求。
+//! 消息内容使用 [`crate::prompt`] 的模板语法和错误类型。
+use super::request::*;
+use crate::prompt::{PromptError, PromptTemplate, PromptVars};
+use std::collections::HashMap;
+
+/// 按 [`crate::prompt`] 的语法渲染一段文本，例如 `{{name}}`、`{{#if name}}...{{/if}}`，不能声明角色。
+pub fn render_str(template: &str, vars: &HashMap<String, String>) -> Result<String, PromptError> {
+    PromptTemplate::parse(template)?.render_str(&PromptVars::from(vars.clone()))
+}
+
+/// 请求模板
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    // 可复用的请求设置，其中的消息会原样保留
+    builder: ChatRequestBuilder,
+    // 消息和解析好的内容模板
+    messages: Vec<(Message, PromptTemplate)>,
+}
+
+impl Default for RequestTemplate {
+    fn default() -> Self {
+        Self::new(ChatRequestBuilder::new())
+    }
+}
+
+impl RequestTemplate {
+    pub fn new(builder: ChatRequestBuilder) -> Self {
+        RequestTemplate {
+            builder,
+            messages: Vec::new(),
+        }
+    }
+    // 由已有请求生成模板，请求中的消息作为固定前缀。
+    pub fn from_request(request: &ChatRequest) -> Self {
+        Self::new(request.to_builder())
+    }
+    // 设置系统提示词，也可以包含 `{{变量}}`。
+    pub fn system_prompt(self, content: &str) -> Result<Self, PromptError> {
+        self.add_message(Message::system_message(content))
+    }
+    // 添加用户消息模板
+    pub fn user_message(self, content: &str) -> Result<Self, PromptError> {
+        self.add_message(Message::user_message(content))
+    }
+    // 添加消息模板，内容在此时解析，语法错误或声明了角色时返回错误。
+    pub fn add_message(mut self, message: Message) -> Result<Self, PromptError> {
+        let template = PromptTemplate::parse(message.content())?;
+        if template.has_roles() {
+            return Err(PromptError::RoleNotAllowed);
+        }
+        self.messages.push((message, template));
+        Ok(self)
+    }
+    // 渲染一组变量，生成请求地址和请求。
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<(String, ChatRequest), PromptError> {
+        let vars = PromptVars::from(vars.clone());
+        let mut builder = self.builder.clone();
+        for (message, template) in &self.messages {
+            let mut message = message.clone();
+            message.set_content(template.render_str(&vars)?);
+            builder = builder.add_message(message);
+        }
+        Ok(builder.build())
+    }
+    // 为每一组输入生成一个请求。
+    pub fn render_all(&self, inputs: &[HashMap<String, String>]) -> Result<Vec<(String, ChatRequest)>, PromptError> {
+        inputs.iter().map(|vars| self.render(vars)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn renders_messages_with_prompt_syntax() {
+        let template = RequestTemplate::default()
+            .system_prompt("你是{{language}}翻译")
+            .unwrap()
+            .user_message(r"{{#if context}}参考：{{context}}。{{/if}}翻译 {{text}}，保留 \{{x}}")
+            .unwrap();
+        let (_, request) = template.render(&vars(&[("language", "英语"), ("text", "你好")])).unwrap();
+        let contents: Vec<&str> = request.messages().iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["你是英语翻译", "翻译 你好，保留 {{x}}"]);
+    }
+
+    #[test]
+    fn shares_prompt_errors() {
+        let template = RequestTemplate::default().user_message("{{a}} {{b}}").unwrap();
+        match template.render(&vars(&[])) {
+            Err(PromptError::MissingVariables(names)) => assert_eq!(names, vec!["a", "b"]),
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(RequestTemplate::default().user_message("{{a"), Err(PromptError::Parse { .. })));
+        assert!(matches!(
+            RequestTemplate::default().user_message("{{#user}}a{{/user}}"),
+            Err(PromptError::RoleNotAllowed)
+        ));
+        assert!(matches!(render_str("{{a", &vars(&[])), Err(PromptError::Parse { .. })));
+    }
+}
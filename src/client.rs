@@ -0,0 +1,502 @@
+//! # 客户端
+//! chat、模型列表和余额接口都通过 [`Transport`] 发送，默认使用 reqwest。
+//!
+//! ```no_run
+//! use deepseek_rs::{DeepSeekClient, chat::*};
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let client = DeepSeekClient::new("sk-...");
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .add_message(Message::user_message("你好"))
+//!     .build();
+//! let response = client.chat(&request).await?;
+//! println!("{:?}", response.content());
+//! # Ok(())
+//! # }
+//! ```
+use crate::auth::{KeyProvider, SecretKey};
+use crate::base_types::data::ModelName;
+pub use crate::base_types::endpoint::{
+    BALANCE_PATH, CHAT_COMPLETIONS_PATH, DEFAULT_BASE_URL, FIM_COMPLETIONS_PATH, MODELS_PATH,
+};
+use crate::cancel::{self, CancelToken};
+use crate::chat::{
+    chunk_stream, json_stream, ChatRequest, ChatRequestBuilder, ChatResponse, ChatStream, Message, OnDrift,
+    RespinseFormat, ToolCall,
+};
+use crate::config::{ClientConfig, ConfigError};
+use crate::completions::{FimRequest, FimResponse, FimStream};
+use crate::error::DeepSeekError;
+use crate::http::{HttpRequest, HttpResponse, ReqwestTransport, Transport};
+use crate::lenient::{self, Drift};
+use crate::middleware::{Middleware, Next, RequestContext};
+use crate::model::{ModelInfo, ModelResponse};
+use crate::provider::{AuthScheme, Capability, Provider};
+use crate::retry::RetryPolicy;
+use crate::timeout::{self, TimeoutError, TimeoutKind, Timeouts};
+use crate::user::BalanceResponse;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+
+/// 模型列表的默认缓存时间
+pub const DEFAULT_MODELS_TTL: Duration = Duration::from_secs(600);
+
+// 缓存的模型列表和获取的时间，clone 出的客户端共享
+type ModelsCache = Arc<Mutex<Option<(Instant, ModelResponse)>>>;
+
+// 严格模式的回调，参数为接口路径和不一致的地方
+type DriftHandler = Arc<dyn Fn(&str, &Drift) + Send + Sync>;
+
+/// DeepSeek 客户端，clone 的开销很小，可以在多个任务间共享
+#[derive(Clone)]
+pub struct DeepSeekClient {
+    key_provider: Arc<dyn KeyProvider>,
+    provider: Provider,
+    base_url: String,
+    transport: Arc<dyn Transport>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    retry: RetryPolicy,
+    timeouts: Timeouts,
+    default_model: ModelName,
+    models: ModelsCache,
+    models_ttl: Duration,
+    strict: Option<DriftHandler>,
+}
+
+impl fmt::Debug for DeepSeekClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeepSeekClient")
+            .field("provider", &self.provider.name())
+            .field("base_url", &self.base_url)
+            .field("default_model", &self.default_model)
+            .field("strict", &self.strict.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeepSeekClient {
+    pub fn new(api_key: &str) -> Self {
+        Self::with_transport(api_key, ReqwestTransport::new())
+    }
+    // 从环境变量创建客户端，见 [`crate::config`]
+    pub fn from_env() -> Result<Self, ConfigError> {
+        ClientConfig::from_env()?.build()
+    }
+    // 使用自定义的传输层
+    pub fn with_transport(api_key: &str, transport: impl Transport + 'static) -> Self {
+        Self::with_key_provider(SecretKey::new(api_key)).transport(transport)
+    }
+    // 每次请求从 provider 取 API Key，见 [`crate::auth`]
+    pub fn with_key_provider(provider: impl KeyProvider + 'static) -> Self {
+        DeepSeekClient {
+            key_provider: Arc::new(provider),
+            provider: Provider::deepseek(),
+            base_url: String::from(DEFAULT_BASE_URL),
+            transport: Arc::new(ReqwestTransport::new()),
+            middlewares: Vec::new(),
+            retry: RetryPolicy::none(),
+            timeouts: Timeouts::default(),
+            default_model: ModelName::DeepseekChat,
+            models: ModelsCache::default(),
+            models_ttl: DEFAULT_MODELS_TTL,
+            strict: None,
+        }
+    }
+    // 修改接口地址，例如指向代理或本地的模拟服务器
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.models = ModelsCache::default();
+        self
+    }
+    pub fn get_base_url(&self) -> &str {
+        &self.base_url
+    }
+    /// 访问其他 OpenAI 兼容服务，同时设置接口地址和默认模型，见 [`crate::provider`]。
+    /// 之后仍然可以用 [`DeepSeekClient::base_url`] 修改地址
+    pub fn provider(mut self, provider: Provider) -> Self {
+        self.base_url = provider.get_base_url().to_string();
+        self.default_model = provider.get_default_model().clone();
+        self.provider = provider;
+        self.models = ModelsCache::default();
+        self
+    }
+    pub fn get_provider(&self) -> &Provider {
+        &self.provider
+    }
+    // 替换传输层
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+    // 替换 API Key 的来源
+    pub fn key_provider(mut self, provider: impl KeyProvider + 'static) -> Self {
+        self.key_provider = Arc::new(provider);
+        self.models = ModelsCache::default();
+        self
+    }
+    // 模型列表的缓存时间，为 0 时不缓存
+    pub fn models_ttl(mut self, ttl: Duration) -> Self {
+        self.models_ttl = ttl;
+        self
+    }
+    // 添加中间件，先添加的在最外层
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+    // 设置重试策略，默认不重试
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+    // 默认超时，请求上设置的超时会覆盖对应的项，见 [`crate::timeout`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    pub fn get_timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+    // 默认模型，用于 [`DeepSeekClient::chat_builder`]
+    pub fn default_model(mut self, model: ModelName) -> Self {
+        self.default_model = model;
+        self
+    }
+    pub fn get_default_model(&self) -> &ModelName {
+        &self.default_model
+    }
+    /// 严格模式，用于测试：响应（包括流式响应的每个 chunk）与响应类型的定义不一致时（未知字段、缺少或为 null 的字段），
+    /// 对每一处调用 `on_drift`，参数为接口路径和不一致的地方。响应仍然按宽松的规则解析，见 [`crate::lenient`]
+    pub fn strict(mut self, on_drift: impl Fn(&str, &Drift) + Send + Sync + 'static) -> Self {
+        self.strict = Some(Arc::new(on_drift));
+        self
+    }
+    // 使用默认模型的请求构建器
+    pub fn chat_builder(&self) -> ChatRequestBuilder {
+        ChatRequestBuilder::new().model(self.default_model.clone())
+    }
+    // 接口的完整地址
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+    // 发送请求，自动添加鉴权请求头；非 2xx 响应返回 [`DeepSeekError::Api`]
+    pub async fn send(&self, request: HttpRequest) -> Result<HttpResponse, DeepSeekError> {
+        let endpoint = request.url().strip_prefix(&self.base_url).unwrap_or(request.url()).to_string();
+        self.execute(request, &RequestContext::new(&endpoint, None)).await
+    }
+    // 发送 chat 请求，请求中的 stream 会被设置为 false
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, DeepSeekError> {
+        let (request, ctx) = self.prepare_chat(request, false)?;
+        let body = request.to_json()?;
+        let mut response: ChatResponse = self
+            .json(
+                self.post(CHAT_COMPLETIONS_PATH, body, request.get_timeouts(), request.get_cancel_token()),
+                &ctx,
+            )
+            .await?;
+        for middleware in &self.middlewares {
+            middleware.on_chat_response(&mut response, &ctx);
+        }
+        Ok(response)
+    }
+    /// JSON 模式：把 response_format 设置为 json_object，并把第一个回复解析为 T。
+    /// 接口要求提示词中包含 "json" 字样和期望的格式示例。
+    /// 模型没有返回内容（或只有空白）时返回 [`DeepSeekError::EmptyContent`]
+    pub async fn chat_json<T: DeserializeOwned>(&self, request: &ChatRequest) -> Result<T, DeepSeekError> {
+        let mut request = request.clone();
+        request.set_response_format(RespinseFormat::json_object());
+        let response = self.chat(&request).await?;
+        let choice = response.choices.first();
+        let Some(content) = choice.and_then(|c| c.get_content()).filter(|c| !c.trim().is_empty()) else {
+            return Err(DeepSeekError::EmptyContent {
+                finish_reason: choice.and_then(|c| c.finish_reason()).map(String::from),
+            });
+        };
+        Ok(serde_json::from_str(content)?)
+    }
+    /// tool 调用循环：模型发起 tool 调用时，用 `call` 执行每个调用，把结果加入对话后继续请求，
+    /// 直到模型不再调用 tool，返回最后的响应。超过 max_rounds 轮仍在调用时返回
+    /// [`DeepSeekError::TooManyToolRounds`]
+    pub async fn run_tools<F, Fut>(
+        &self,
+        request: &ChatRequest,
+        max_rounds: usize,
+        mut call: F,
+    ) -> Result<ChatResponse, DeepSeekError>
+    where
+        F: FnMut(ToolCall) -> Fut,
+        Fut: Future<Output = String>,
+    {
+        let mut request = request.clone();
+        let mut rounds = 0;
+        loop {
+            let response = self.chat(&request).await?;
+            let Some(choice) = response.choices.first().filter(|choice| !choice.tool_calls().is_empty()) else {
+                return Ok(response);
+            };
+            if rounds == max_rounds {
+                return Err(DeepSeekError::TooManyToolRounds { rounds: max_rounds });
+            }
+            rounds += 1;
+            request.add_message(choice.to_message());
+            for tool_call in choice.tool_calls() {
+                let output = call(tool_call.clone()).await;
+                request.add_message(Message::tool_message(tool_call.id(), &output));
+            }
+        }
+    }
+    // 发送流式 chat 请求，请求中的 stream 会被设置为 true
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream, DeepSeekError> {
+        let (request, ctx) = self.prepare_chat(request, true)?;
+        let body = request.to_json()?;
+        let response = self
+            .execute(
+                self.post(CHAT_COMPLETIONS_PATH, body, request.get_timeouts(), request.get_cancel_token()),
+                &ctx,
+            )
+            .await?;
+        let middlewares = self.middlewares.clone();
+        Ok(chunk_stream(response.into_body(), self.stream_drift(CHAT_COMPLETIONS_PATH))
+            .map(move |chunk| {
+                chunk.map(|mut chunk| {
+                    if chunk.has_output() {
+                        ctx.mark_first_token();
+                    }
+                    for middleware in &middlewares {
+                        middleware.on_chunk(&mut chunk, &ctx);
+                    }
+                    chunk
+                })
+            })
+            .boxed())
+    }
+    // 发送 FIM 补全请求，请求中的 stream 会被设置为 false
+    pub async fn fim(&self, request: &FimRequest) -> Result<FimResponse, DeepSeekError> {
+        self.require(Capability::Fim)?;
+        let mut request = request.clone();
+        request.set_stream(false);
+        let model = self.provider.model_id(request.model()).to_string();
+        request.set_model(&model);
+        let ctx = RequestContext::new(FIM_COMPLETIONS_PATH, Some(request.model()));
+        let http = self.post(
+            FIM_COMPLETIONS_PATH,
+            request.to_json()?,
+            request.get_timeouts(),
+            request.get_cancel_token(),
+        );
+        let mut response: FimResponse = self.json(http, &ctx).await?;
+        for middleware in &self.middlewares {
+            middleware.on_fim_response(&mut response, &ctx);
+        }
+        Ok(response)
+    }
+    // 发送流式 FIM 补全请求，请求中的 stream 会被设置为 true
+    pub async fn fim_stream(&self, request: &FimRequest) -> Result<FimStream, DeepSeekError> {
+        self.require(Capability::Fim)?;
+        let mut request = request.clone();
+        request.set_stream(true);
+        let model = self.provider.model_id(request.model()).to_string();
+        request.set_model(&model);
+        let ctx = RequestContext::new(FIM_COMPLETIONS_PATH, Some(request.model()));
+        let http = self.post(
+            FIM_COMPLETIONS_PATH,
+            request.to_json()?,
+            request.get_timeouts(),
+            request.get_cancel_token(),
+        );
+        let response = self.execute(http, &ctx).await?;
+        let middlewares = self.middlewares.clone();
+        Ok(json_stream(response.into_body(), self.stream_drift(FIM_COMPLETIONS_PATH))
+            .map(move |chunk| {
+                chunk.map(|mut chunk: FimResponse| {
+                    for middleware in &middlewares {
+                        middleware.on_fim_response(&mut chunk, &ctx);
+                    }
+                    chunk
+                })
+            })
+            .boxed())
+    }
+    // 列出可用的模型
+    // 列表会缓存 [`DeepSeekClient::models_ttl`]（默认 10 分钟），过期后重新获取
+    pub async fn models(&self) -> Result<ModelResponse, DeepSeekError> {
+        match self.cached_models() {
+            Some(models) => Ok(models),
+            None => self.refresh_models().await,
+        }
+    }
+    // 忽略缓存重新获取模型列表，例如轮换了 API Key 之后
+    pub async fn refresh_models(&self) -> Result<ModelResponse, DeepSeekError> {
+        self.require(Capability::Models)?;
+        let ctx = RequestContext::new(MODELS_PATH, None);
+        let models: ModelResponse = self.json(HttpRequest::get(&self.url(MODELS_PATH)), &ctx).await?;
+        *self.models.lock().unwrap() = Some((Instant::now(), models.clone()));
+        Ok(models)
+    }
+    /// 检查当前 API Key 能否使用该模型，不能使用时返回 [`DeepSeekError::ModelUnavailable`]。
+    /// 缓存的列表中没有该模型时会重新获取一次，以免新上线的模型被误判
+    pub async fn ensure_model(&self, model: &str) -> Result<ModelInfo, DeepSeekError> {
+        let model = self.provider.model_id(model);
+        let models = match self.cached_models() {
+            Some(models) if models.contains(model) => models,
+            _ => self.refresh_models().await?,
+        };
+        models.get(model).cloned().ok_or_else(|| DeepSeekError::ModelUnavailable {
+            model: model.to_string(),
+            available: models.ids().into_iter().map(String::from).collect(),
+        })
+    }
+    // 检查默认模型是否可用，适合在启动时调用
+    pub async fn ensure_default_model(&self) -> Result<ModelInfo, DeepSeekError> {
+        self.ensure_model(self.default_model.as_str()).await
+    }
+    // 查询账户余额
+    pub async fn balance(&self) -> Result<BalanceResponse, DeepSeekError> {
+        self.require(Capability::Balance)?;
+        let ctx = RequestContext::new(BALANCE_PATH, None);
+        self.json(HttpRequest::get(&self.url(BALANCE_PATH)), &ctx).await
+    }
+
+    fn prepare_chat(&self, request: &ChatRequest, stream: bool) -> Result<(ChatRequest, RequestContext), DeepSeekError> {
+        if request.tools().is_some_and(|tools| !tools.is_empty()) {
+            self.require(Capability::Tools)?;
+        }
+        if request.response_format().is_some_and(|format| format.format_type() == "json_object") {
+            self.require(Capability::JsonMode)?;
+        }
+        let mut request = request.clone();
+        request.set_stream(stream);
+        let model = self.provider.model_id(request.model()).to_string();
+        request.set_model(&model);
+        let ctx = RequestContext::new(CHAT_COMPLETIONS_PATH, Some(request.model()));
+        for middleware in &self.middlewares {
+            middleware.on_chat_request(&mut request, &ctx);
+        }
+        Ok((request, ctx))
+    }
+
+    // provider 不支持该功能时返回错误
+    fn require(&self, capability: Capability) -> Result<(), DeepSeekError> {
+        if self.provider.supports(capability) {
+            return Ok(());
+        }
+        Err(DeepSeekError::Unsupported {
+            provider: self.provider.name().to_string(),
+            capability,
+        })
+    }
+
+    // 没有过期的模型列表缓存
+    fn cached_models(&self) -> Option<ModelResponse> {
+        let cache = self.models.lock().unwrap();
+        let (fetched, models) = cache.as_ref()?;
+        (fetched.elapsed() < self.models_ttl).then(|| models.clone())
+    }
+
+    // 带上请求级的超时和取消令牌
+    fn post(&self, path: &str, body: String, timeouts: &Timeouts, cancel: Option<&CancelToken>) -> HttpRequest {
+        let request = HttpRequest::post(&self.url(path), body).timeouts(*timeouts);
+        match cancel {
+            Some(token) => request.cancel_token(token.clone()),
+            None => request,
+        }
+    }
+
+    async fn execute(&self, request: HttpRequest, ctx: &RequestContext) -> Result<HttpResponse, DeepSeekError> {
+        let Some(token) = request.get_cancel_token().cloned() else {
+            return self.execute_with_retry(request, ctx).await;
+        };
+        // 取消时丢弃正在发送的请求（关闭连接）和重试前的等待
+        let response = tokio::select! {
+            biased;
+            _ = token.cancelled() => return Err(DeepSeekError::Cancelled),
+            response = self.execute_with_retry(request, ctx) => response?,
+        };
+        Ok(response.map_body(|body| cancel::guard_body(body, token)))
+    }
+
+    async fn execute_with_retry(&self, request: HttpRequest, ctx: &RequestContext) -> Result<HttpResponse, DeepSeekError> {
+        let timeouts = self.timeouts.merge(*request.get_timeouts());
+        let request = request.header("Content-Type", "application/json").timeouts(timeouts);
+        let mut attempt = 0;
+        // 包括换 key 重试在内的发送次数
+        let mut tries = 0;
+        loop {
+            let ctx = ctx.clone().with_attempt(tries);
+            tries += 1;
+            let key = match self.provider.get_auth() {
+                AuthScheme::None => None,
+                _ => Some(self.key_provider.key()?),
+            };
+            let request = match (self.provider.get_auth(), &key) {
+                (AuthScheme::Bearer, Some(key)) => {
+                    request.clone().secret_header("Authorization", &format!("Bearer {}", key.expose()))
+                }
+                (AuthScheme::Header(name), Some(key)) => request.clone().secret_header(name, key.expose()),
+                _ => request.clone(),
+            };
+            // 首字节超时只限制收到响应头之前，总超时一直持续到响应体读完
+            let deadline = timeout::deadline(&timeouts);
+            let first_byte = timeouts
+                .get_first_byte()
+                .map(|after| (Instant::now() + after, TimeoutError::new(TimeoutKind::FirstByte, after)));
+            let sent = timeout::within(
+                Next::new(&self.middlewares, self.transport.as_ref(), &ctx).run(request),
+                first_byte.into_iter().chain(deadline),
+            )
+            .await
+            .unwrap_or_else(|err| Err(err.into()))
+            .map(|response| response.map_body(|body| timeout::guard_body(body, timeouts.get_idle(), deadline)));
+            let (err, retry_after) = match sent {
+                Ok(response) if response.is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = response.retry_after();
+                    let body = response.text().await.unwrap_or_default();
+                    (DeepSeekError::api(status, body), retry_after)
+                }
+                Err(err) => (DeepSeekError::from(err), None),
+            };
+            // key 被拒绝且 provider 换了 key 时立即重试，不计入重试次数
+            if let Some(status @ (401 | 402)) = err.status()
+                && let Some(key) = &key
+                && self.key_provider.reject(key, status)
+            {
+                continue;
+            }
+            if attempt >= self.retry.get_max_retries() || !RetryPolicy::is_retryable(&err) {
+                return Err(err);
+            }
+            attempt += 1;
+            tokio::time::sleep(self.retry.delay(attempt, retry_after)).await;
+        }
+    }
+
+    // 严格模式下流式响应使用的回调
+    fn stream_drift(&self, endpoint: &'static str) -> Option<OnDrift> {
+        let on_drift = self.strict.clone()?;
+        Some(Box::new(move |drift: &Drift| on_drift(endpoint, drift)))
+    }
+
+    async fn json<T: DeserializeOwned + Serialize>(
+        &self,
+        request: HttpRequest,
+        ctx: &RequestContext,
+    ) -> Result<T, DeepSeekError> {
+        let text = self.execute(request, ctx).await?.text().await?;
+        let Some(on_drift) = &self.strict else {
+            return Ok(serde_json::from_str(&text)?);
+        };
+        let (value, drift) = lenient::check(&text)?;
+        for drift in &drift {
+            on_drift(ctx.endpoint(), drift);
+        }
+        Ok(value)
+    }
+}
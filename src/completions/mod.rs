@@ -0,0 +1,9 @@
+//! # FIM 补全（Beta）
+//! 给定前缀 `prompt` 和可选的后缀 `suffix`，由模型补全中间的内容，常用于代码补全。
+pub mod request;
+pub mod response;
+
+pub use request::{FimRequest, FimRequestBuilder};
+pub use response::{FimChoice, FimResponse};
+#[cfg(feature = "client")]
+pub use response::FimStream;
@@ -0,0 +1,167 @@
+use crate::base_types::data::ModelName;
+use crate::chat::Stop;
+#[cfg(feature = "client")]
+use crate::cancel::CancelToken;
+use crate::timeout::Timeouts;
+use serde::{Deserialize, Serialize};
+
+/// FIM 补全请求
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FimRequest {
+    // 使用的模型的 ID，目前只支持 deepseek-chat。
+    model: String,
+    // 用于生成补全内容的前缀。
+    prompt: String,
+    // 补全内容的后缀。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    // 是否在输出中把 prompt 的内容也输出出来。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    echo: Option<bool>,
+    // 介于 -2.0 和 2.0 之间的数字，含义与 chat 接口相同。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+    // 最多生成的 token 数，最大为 4096。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    // 介于 -2.0 和 2.0 之间的数字，含义与 chat 接口相同。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    // 一个 string 或最多包含 16 个 string 的 list，在遇到这些词时，API 将停止生成更多的 token。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Stop>,
+    // 如果设置为 True，将会以 SSE（server-sent events）的形式以流式发送增量。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    // 采样温度，介于 0 和 2 之间。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    // 作为调节采样温度的替代方案，模型会考虑前 top_p 概率的 token 的结果。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    // 本次请求的超时，不会发送给接口
+    #[serde(skip)]
+    timeouts: Timeouts,
+    // 取消令牌，不会发送给接口
+    #[cfg(feature = "client")]
+    #[serde(skip)]
+    cancel: Option<CancelToken>,
+}
+
+impl FimRequest {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+    pub fn set_stream(&mut self, stream: bool) {
+        self.stream = Some(stream);
+    }
+    // 修改模型 id，例如换成 provider 上的模型名称
+    pub fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+    }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+    pub fn suffix(&self) -> Option<&str> {
+        self.suffix.as_deref()
+    }
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+    pub fn stream(&self) -> Option<bool> {
+        self.stream
+    }
+    pub fn get_timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+    #[cfg(feature = "client")]
+    pub fn get_cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
+}
+
+/// FIM 补全请求构建器
+#[derive(Debug, Clone)]
+pub struct FimRequestBuilder {
+    request: FimRequest,
+}
+
+impl FimRequestBuilder {
+    pub fn new(prompt: &str) -> Self {
+        FimRequestBuilder {
+            request: FimRequest {
+                model: ModelName::DeepseekChat.to_string(),
+                prompt: prompt.to_string(),
+                suffix: None,
+                echo: None,
+                frequency_penalty: None,
+                max_tokens: None,
+                presence_penalty: None,
+                stop: None,
+                stream: None,
+                temperature: None,
+                top_p: None,
+                timeouts: Timeouts::default(),
+                #[cfg(feature = "client")]
+                cancel: None,
+            },
+        }
+    }
+    pub fn model(mut self, model: ModelName) -> Self {
+        self.request.model = model.to_string();
+        self
+    }
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.request.suffix = Some(suffix.to_string());
+        self
+    }
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.request.echo = Some(echo);
+        self
+    }
+    pub fn frequency_penalty(mut self, frequency_penalty: f64) -> Self {
+        self.request.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.request.max_tokens = Some(max_tokens);
+        self
+    }
+    pub fn presence_penalty(mut self, presence_penalty: f64) -> Self {
+        self.request.presence_penalty = Some(presence_penalty);
+        self
+    }
+    pub fn stop(mut self, stop: impl Into<Stop>) -> Self {
+        self.request.stop = Some(stop.into());
+        self
+    }
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.request.stream = Some(stream);
+        self
+    }
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.request.temperature = Some(temperature);
+        self
+    }
+    pub fn top_p(mut self, top_p: f64) -> Self {
+        self.request.top_p = Some(top_p);
+        self
+    }
+    // 覆盖客户端上设置的超时，见 [`crate::timeout`]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.request.timeouts = timeouts;
+        self
+    }
+    #[cfg(feature = "client")]
+    // 取消令牌，见 [`crate::cancel`]
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.request.cancel = Some(token);
+        self
+    }
+    pub fn build(self) -> (String, FimRequest) {
+        (String::from("https://api.deepseek.com/beta/completions"), self.request)
+    }
+}
@@ -0,0 +1,96 @@
+use crate::chat::Usage;
+#[cfg(feature = "client")]
+use crate::error::DeepSeekError;
+use crate::lenient::{null_default, skip_extra, Extra};
+#[cfg(feature = "client")]
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+
+/// FIM 流式响应，每个 chunk 的结构与完整响应相同，text 为本次新增的内容
+#[cfg(feature = "client")]
+pub type FimStream = BoxStream<'static, Result<FimResponse, DeepSeekError>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FimResponse {
+    // 补全响应的 ID。
+    #[serde(deserialize_with = "null_default")]
+    id: String,
+    // 模型生成的补全内容的选择列表。
+    #[serde(deserialize_with = "null_default")]
+    choices: Vec<FimChoice>,
+    // 创建补全时的 Unix 时间戳（以秒为单位）。
+    #[serde(deserialize_with = "null_default")]
+    created: isize,
+    // 补全请求所用的模型。
+    #[serde(deserialize_with = "null_default")]
+    model: String,
+    // 模型运行时的后端配置的指纹。
+    system_fingerprint: Option<String>,
+    // 对象的类型, 其值为 text_completion。
+    #[serde(deserialize_with = "null_default")]
+    object: String,
+    // 该补全请求的用量信息，流式响应中只在最后一个 chunk 中出现。
+    usage: Option<Usage>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl FimResponse {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn choices(&self) -> &[FimChoice] {
+        &self.choices
+    }
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 每个 choice 的补全内容
+    pub fn text(&self) -> Vec<&str> {
+        self.choices.iter().map(|c| c.text()).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FimChoice {
+    // 该 choice 在列表中的索引。
+    #[serde(deserialize_with = "null_default")]
+    index: usize,
+    // 补全的内容。
+    #[serde(deserialize_with = "null_default")]
+    text: String,
+    // 对数概率信息。
+    logprobs: Option<serde_json::Value>,
+    // 模型停止生成 token 的原因，流式响应中只在最后一个 chunk 中出现。
+    finish_reason: Option<String>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
+}
+
+impl FimChoice {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn logprobs(&self) -> Option<&serde_json::Value> {
+        self.logprobs.as_ref()
+    }
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+}
@@ -0,0 +1,418 @@
+//! # 配置
+//! 从环境变量和 TOML 配置文件创建客户端。优先级从高到低为：环境变量、所选 profile、配置文件顶层的默认值。
+//!
+//! | 环境变量 | 含义 |
+//! | --- | --- |
+//! | `DEEPSEEK_API_KEY` | API Key |
+//! | `DEEPSEEK_BASE_URL` | 接口地址 |
+//! | `DEEPSEEK_MODEL` | 默认模型 |
+//! | `DEEPSEEK_TIMEOUT` | 请求超时（秒） |
+//! | `DEEPSEEK_CONNECT_TIMEOUT` | 连接超时（秒） |
+//! | `DEEPSEEK_FIRST_BYTE_TIMEOUT` | 首字节超时（秒） |
+//! | `DEEPSEEK_IDLE_TIMEOUT` | 流式响应的空闲超时（秒） |
+//! | `DEEPSEEK_PROXY` | 代理地址；没有设置时 reqwest 使用 `HTTPS_PROXY` 等系统代理变量 |
+//! | `DEEPSEEK_PROFILE` | 使用的 profile |
+//! | `DEEPSEEK_CONFIG` | 配置文件路径，默认为 `~/.config/deepseek/config.toml` |
+//!
+//! 配置文件示例：
+//!
+//! ```toml
+//! model = "deepseek-chat"
+//! timeout = 60
+//! connect_timeout = 5
+//!
+//! [profiles.dev]
+//! base_url = "http://localhost:8080"
+//! api_key = "sk-dev"
+//!
+//! [profiles.prod]
+//! api_key = "sk-prod"
+//! model = "deepseek-reasoner"
+//! timeout = 600
+//! idle_timeout = 60
+//! ```
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::config::ClientConfig;
+//!
+//! # fn run() -> Result<(), deepseek_rs::config::ConfigError> {
+//! // 只使用环境变量
+//! let client = DeepSeekClient::from_env()?;
+//! // 配置文件 + DEEPSEEK_PROFILE 指定的 profile + 环境变量
+//! let client = ClientConfig::load(None)?.build()?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::auth::SecretKey;
+use crate::base_types::data::ModelName;
+use crate::client::DeepSeekClient;
+use crate::http::ReqwestTransport;
+use crate::timeout::Timeouts;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const API_KEY_ENV: &str = "DEEPSEEK_API_KEY";
+pub const BASE_URL_ENV: &str = "DEEPSEEK_BASE_URL";
+pub const MODEL_ENV: &str = "DEEPSEEK_MODEL";
+pub const TIMEOUT_ENV: &str = "DEEPSEEK_TIMEOUT";
+pub const CONNECT_TIMEOUT_ENV: &str = "DEEPSEEK_CONNECT_TIMEOUT";
+pub const FIRST_BYTE_TIMEOUT_ENV: &str = "DEEPSEEK_FIRST_BYTE_TIMEOUT";
+pub const IDLE_TIMEOUT_ENV: &str = "DEEPSEEK_IDLE_TIMEOUT";
+pub const PROXY_ENV: &str = "DEEPSEEK_PROXY";
+pub const PROFILE_ENV: &str = "DEEPSEEK_PROFILE";
+pub const CONFIG_ENV: &str = "DEEPSEEK_CONFIG";
+
+/// 配置错误
+#[derive(Debug)]
+pub enum ConfigError {
+    // 无法读取配置文件
+    Io { path: PathBuf, source: std::io::Error },
+    // 配置文件格式错误
+    Parse { path: PathBuf, message: String },
+    // 配置文件中没有该 profile
+    UnknownProfile(String),
+    // 没有设置 API Key
+    MissingApiKey,
+    // 配置项的值无效，name 为配置项或环境变量名
+    InvalidValue { name: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "无法读取配置文件 {}: {}", path.display(), source),
+            ConfigError::Parse { path, message } => write!(f, "配置文件 {} 格式错误: {}", path.display(), message),
+            ConfigError::UnknownProfile(name) => write!(f, "配置文件中没有 profile: {}", name),
+            ConfigError::MissingApiKey => write!(f, "没有设置 API Key，请设置环境变量 {} 或在配置文件中设置 api_key", API_KEY_ENV),
+            ConfigError::InvalidValue { name, message } => write!(f, "{} 的值无效: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// 客户端配置，未设置的项使用默认值；`Debug` 输出中不包含 API Key。
+/// 配置文件中的未知项（例如拼错的 `tmeout`）和无效的超时会作为格式错误返回
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    api_key: Option<SecretKey>,
+    base_url: Option<String>,
+    model: Option<String>,
+    // 请求超时，配置文件中以秒为单位
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    timeout: Option<Duration>,
+    // 连接超时
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    connect_timeout: Option<Duration>,
+    // 首字节超时
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    first_byte_timeout: Option<Duration>,
+    // 空闲超时
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    idle_timeout: Option<Duration>,
+    proxy: Option<String>,
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // 只读取环境变量
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(ClientConfig {
+            api_key: env(API_KEY_ENV).map(SecretKey::from),
+            base_url: env(BASE_URL_ENV),
+            model: env(MODEL_ENV),
+            timeout: env(TIMEOUT_ENV).map(|v| parse_secs(TIMEOUT_ENV, &v)).transpose()?,
+            connect_timeout: env(CONNECT_TIMEOUT_ENV)
+                .map(|v| parse_secs(CONNECT_TIMEOUT_ENV, &v))
+                .transpose()?,
+            first_byte_timeout: env(FIRST_BYTE_TIMEOUT_ENV)
+                .map(|v| parse_secs(FIRST_BYTE_TIMEOUT_ENV, &v))
+                .transpose()?,
+            idle_timeout: env(IDLE_TIMEOUT_ENV)
+                .map(|v| parse_secs(IDLE_TIMEOUT_ENV, &v))
+                .transpose()?,
+            proxy: env(PROXY_ENV),
+        })
+    }
+    /// 读取配置文件（`DEEPSEEK_CONFIG` 或默认路径，不存在时忽略）中的 profile，再用环境变量覆盖。
+    /// profile 为 None 时使用 `DEEPSEEK_PROFILE`，也没有设置时只使用配置文件顶层的默认值
+    pub fn load(profile: Option<&str>) -> Result<Self, ConfigError> {
+        let file = match env(CONFIG_ENV).map(PathBuf::from) {
+            Some(path) => ConfigFile::load(path)?,
+            None => match ConfigFile::default_path() {
+                Some(path) if path.exists() => ConfigFile::load(path)?,
+                _ => ConfigFile::default(),
+            },
+        };
+        let profile = profile.map(String::from).or_else(|| env(PROFILE_ENV));
+        Ok(file.profile(profile.as_deref())?.merge(Self::from_env()?))
+    }
+    // 用 other 中设置了的项覆盖当前配置
+    pub fn merge(self, other: ClientConfig) -> Self {
+        ClientConfig {
+            api_key: other.api_key.or(self.api_key),
+            base_url: other.base_url.or(self.base_url),
+            model: other.model.or(self.model),
+            timeout: other.timeout.or(self.timeout),
+            connect_timeout: other.connect_timeout.or(self.connect_timeout),
+            first_byte_timeout: other.first_byte_timeout.or(self.first_byte_timeout),
+            idle_timeout: other.idle_timeout.or(self.idle_timeout),
+            proxy: other.proxy.or(self.proxy),
+        }
+    }
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(SecretKey::new(api_key));
+        self
+    }
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+    pub fn model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    pub fn first_byte_timeout(mut self, timeout: Duration) -> Self {
+        self.first_byte_timeout = Some(timeout);
+        self
+    }
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+    pub fn get_api_key(&self) -> Option<&SecretKey> {
+        self.api_key.as_ref()
+    }
+    pub fn get_base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+    pub fn get_model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+    pub fn get_connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout
+    }
+    pub fn get_first_byte_timeout(&self) -> Option<Duration> {
+        self.first_byte_timeout
+    }
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+    // 所有超时设置
+    pub fn get_timeouts(&self) -> Timeouts {
+        let mut timeouts = Timeouts::new();
+        if let Some(timeout) = self.get_connect_timeout() {
+            timeouts = timeouts.connect(timeout);
+        }
+        if let Some(timeout) = self.get_timeout() {
+            timeouts = timeouts.total(timeout);
+        }
+        if let Some(timeout) = self.get_first_byte_timeout() {
+            timeouts = timeouts.first_byte(timeout);
+        }
+        if let Some(timeout) = self.get_idle_timeout() {
+            timeouts = timeouts.idle(timeout);
+        }
+        timeouts
+    }
+    pub fn get_proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// 按配置创建客户端
+    pub fn build(&self) -> Result<DeepSeekClient, ConfigError> {
+        let api_key = self.api_key.clone().ok_or(ConfigError::MissingApiKey)?;
+        let invalid = |name: &str, message: String| ConfigError::InvalidValue {
+            name: name.to_string(),
+            message,
+        };
+        let proxy = self
+            .proxy
+            .as_deref()
+            .map(reqwest::Proxy::all)
+            .transpose()
+            .map_err(|e| invalid("proxy", e.to_string()))?;
+        let transport = ReqwestTransport::from_builder(move || match &proxy {
+            Some(proxy) => reqwest::Client::builder().proxy(proxy.clone()),
+            None => reqwest::Client::builder(),
+        })
+        .map_err(|e| invalid("http", e.to_string()))?;
+        let mut client = DeepSeekClient::with_key_provider(api_key)
+            .transport(transport)
+            .timeouts(self.get_timeouts());
+        if let Some(base_url) = &self.base_url {
+            client = client.base_url(base_url);
+        }
+        if let Some(model) = &self.model {
+            client = client.default_model(ModelName::from_id(model));
+        }
+        Ok(client)
+    }
+}
+
+/// TOML 配置文件：顶层为默认值，`[profiles.<名称>]` 中的项覆盖默认值
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(try_from = "RawConfigFile")]
+pub struct ConfigFile {
+    defaults: ClientConfig,
+    profiles: HashMap<String, ClientConfig>,
+}
+
+// flatten 会让 ClientConfig 上的 deny_unknown_fields 失效，所以先把顶层的项收集起来，再单独解析
+#[derive(Deserialize)]
+struct RawConfigFile {
+    #[serde(flatten)]
+    defaults: toml::Table,
+    #[serde(default)]
+    profiles: HashMap<String, ClientConfig>,
+}
+
+impl TryFrom<RawConfigFile> for ConfigFile {
+    type Error = toml::de::Error;
+
+    fn try_from(raw: RawConfigFile) -> Result<Self, Self::Error> {
+        Ok(ConfigFile {
+            defaults: ClientConfig::deserialize(toml::Value::Table(raw.defaults))?,
+            profiles: raw.profiles,
+        })
+    }
+}
+
+impl std::str::FromStr for ConfigFile {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl ConfigFile {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        text.parse().map_err(|e: toml::de::Error| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.message().trim_end().to_string(),
+        })
+    }
+    // 默认路径：`$XDG_CONFIG_HOME/deepseek/config.toml`，Windows 上为 `%APPDATA%\deepseek\config.toml`，
+    // 其他情况为 `~/.config/deepseek/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("deepseek").join("config.toml"))
+    }
+    pub fn defaults(&self) -> &ClientConfig {
+        &self.defaults
+    }
+    pub fn profiles(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+    // 合并默认值和 profile，None 时只使用默认值
+    pub fn profile(&self, name: Option<&str>) -> Result<ClientConfig, ConfigError> {
+        match name {
+            Some(name) => {
+                let profile = self
+                    .profiles
+                    .get(name)
+                    .ok_or_else(|| ConfigError::UnknownProfile(name.to_string()))?;
+                Ok(self.defaults.clone().merge(profile.clone()))
+            }
+            None => Ok(self.defaults.clone()),
+        }
+    }
+}
+
+// 读取非空的环境变量
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn parse_secs(name: &str, value: &str) -> Result<Duration, ConfigError> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(secs_to_duration)
+        .ok_or_else(|| ConfigError::InvalidValue {
+            name: name.to_string(),
+            message: format!("需要正数秒数，实际为 {}", value),
+        })
+}
+
+// 负数、0、inf、NaN 和超出范围的值返回 None
+fn secs_to_duration(secs: f64) -> Option<Duration> {
+    Some(secs)
+        .filter(|secs| *secs > 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+fn deserialize_secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let secs = f64::deserialize(deserializer)?;
+    secs_to_duration(secs)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("超时需要正数秒数，实际为 {}", secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!("tmeout = 5".parse::<ConfigFile>().is_err());
+        assert!("[profiles.dev]\ntmeout = 5".parse::<ConfigFile>().is_err());
+        let file: ConfigFile = "timeout = 5\n[profiles.dev]\nmodel = \"deepseek-reasoner\"".parse().unwrap();
+        assert_eq!(file.defaults().get_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(file.profile(Some("dev")).unwrap().get_model(), Some("deepseek-reasoner"));
+    }
+
+    #[test]
+    fn rejects_invalid_timeouts() {
+        for value in ["-1", "0", "inf", "nan", "1e30"] {
+            let err = format!("timeout = {}", value).parse::<ConfigFile>().unwrap_err();
+            assert!(err.message().contains("正数秒数"), "{}: {}", value, err);
+        }
+        assert!(parse_secs(TIMEOUT_ENV, "-1").is_err());
+    }
+
+    #[test]
+    fn accepts_custom_models() {
+        let client = ClientConfig::new().api_key("sk-test").model("qwen2.5-coder").build().unwrap();
+        assert_eq!(client.get_default_model().as_str(), "qwen2.5-coder");
+    }
+}
@@ -0,0 +1,135 @@
+//! # 错误类型
+use crate::cancel::CancelledError;
+use crate::provider::Capability;
+use crate::timeout::{TimeoutError, TimeoutKind};
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+/// 客户端错误
+#[derive(Debug)]
+pub enum DeepSeekError {
+    // 网络或传输层错误
+    Transport(std::io::Error),
+    // 接口返回了非 2xx 状态码，message 为接口返回的错误信息
+    Api {
+        status: u16,
+        message: String,
+        body: String,
+    },
+    // 响应无法解析为 JSON
+    Json(serde_json::Error),
+    // 流式响应格式错误或中途断开
+    Stream(String),
+    // 超时，kind 为超时的种类，after 为设置的超时时间，见 [`crate::timeout`]
+    Timeout { kind: TimeoutKind, after: Duration },
+    // 当前 API Key 无法使用该模型，available 为可用的模型
+    ModelUnavailable { model: String, available: Vec<String> },
+    // 请求被取消，见 [`crate::cancel`]
+    Cancelled,
+    // 无法取得 API Key，见 [`crate::auth::KeyProvider`]
+    Auth(String),
+    // tool 调用循环超过了允许的轮数
+    TooManyToolRounds { rounds: usize },
+    // JSON 模式下模型没有返回内容，例如 finish_reason 为 length
+    EmptyContent { finish_reason: Option<String> },
+    // provider 不支持该功能，没有发送请求，见 [`crate::provider`]
+    Unsupported { provider: String, capability: Capability },
+}
+
+impl DeepSeekError {
+    // 由非 2xx 响应体生成错误，尽量取出接口返回的 error.message
+    pub fn api(status: u16, body: String) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            error: ErrorDetail,
+        }
+        #[derive(Deserialize)]
+        struct ErrorDetail {
+            message: String,
+        }
+        let message = serde_json::from_str::<ErrorBody>(&body)
+            .map(|b| b.error.message)
+            .unwrap_or_else(|_| body.clone());
+        DeepSeekError::Api { status, message, body }
+    }
+    // 接口返回的状态码
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            DeepSeekError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+    // 超时的种类，不是超时错误时为 None
+    pub fn timeout_kind(&self) -> Option<TimeoutKind> {
+        match self {
+            DeepSeekError::Timeout { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl From<CancelledError> for DeepSeekError {
+    fn from(_: CancelledError) -> Self {
+        DeepSeekError::Cancelled
+    }
+}
+
+impl From<TimeoutError> for DeepSeekError {
+    fn from(err: TimeoutError) -> Self {
+        DeepSeekError::Timeout {
+            kind: err.kind,
+            after: err.after,
+        }
+    }
+}
+
+impl fmt::Display for DeepSeekError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeepSeekError::Transport(err) => write!(f, "请求失败: {}", err),
+            DeepSeekError::Api { status, message, .. } => write!(f, "接口返回错误 {}: {}", status, message),
+            DeepSeekError::Json(err) => write!(f, "响应解析失败: {}", err),
+            DeepSeekError::Stream(message) => write!(f, "流式响应错误: {}", message),
+            DeepSeekError::Timeout { kind, after } => write!(f, "请求{}: {:?}", kind, after),
+            DeepSeekError::ModelUnavailable { model, available } => {
+                write!(f, "当前 API Key 无法使用模型 {}，可用的模型: {}", model, available.join(", "))
+            }
+            DeepSeekError::Cancelled => write!(f, "请求已取消"),
+            DeepSeekError::Auth(message) => write!(f, "API Key 错误: {}", message),
+            DeepSeekError::TooManyToolRounds { rounds } => write!(f, "tool 调用超过 {} 轮仍未结束", rounds),
+            DeepSeekError::EmptyContent { finish_reason } => {
+                write!(f, "模型没有返回内容，finish_reason: {}", finish_reason.as_deref().unwrap_or("无"))
+            }
+            DeepSeekError::Unsupported { provider, capability } => write!(f, "{} 不支持 {}", provider, capability),
+        }
+    }
+}
+
+impl std::error::Error for DeepSeekError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeepSeekError::Transport(err) => Some(err),
+            DeepSeekError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DeepSeekError {
+    fn from(err: std::io::Error) -> Self {
+        if CancelledError::is_cancelled(&err) {
+            return DeepSeekError::Cancelled;
+        }
+        match TimeoutError::from_io(&err) {
+            Some(TimeoutError { kind, after }) => DeepSeekError::Timeout { kind, after },
+            None => DeepSeekError::Transport(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for DeepSeekError {
+    fn from(err: serde_json::Error) -> Self {
+        DeepSeekError::Json(err)
+    }
+}
@@ -0,0 +1,364 @@
+//! # 录制与回放
+//! 录制模式下请求会转发到真实接口，请求与响应（包括 SSE 每个 chunk 的间隔）写入 cassette 文件，
+//! 包含密钥的请求头（[`super::SENSITIVE_HEADERS`] 和 provider 设置的鉴权请求头）会被脱敏；回放模式下按请求方法、路径和规范化后的 JSON 请求体匹配录制的响应。
+//! 没有读到结尾（被取消、丢弃或读取出错，SSE 响应以 `data: [DONE]` 为结尾）的响应标记为 incomplete，回放时在录制的 chunk 之后返回读取错误。
+use super::transport::{ByteStream, HttpRequest, HttpResponse, Transport};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// 脱敏后的请求头的值，与鉴权方式无关
+pub const REDACTED: &str = "[REDACTED]";
+
+/// cassette 文件内容
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        std::fs::write(path, text)
+    }
+    pub fn interactions(&self) -> &[Interaction] {
+        &self.interactions
+    }
+}
+
+/// 一次请求与对应的响应
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+impl Interaction {
+    pub fn request(&self) -> &RecordedRequest {
+        &self.request
+    }
+    pub fn response(&self) -> &RecordedResponse {
+        &self.response
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedRequest {
+    method: String,
+    // 只保存路径，回放时不关心 base url
+    path: String,
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+impl RecordedRequest {
+    // 包含密钥的请求头（见 [`HttpRequest::is_sensitive`]）会被替换为 [`REDACTED`]
+    pub fn new(request: &HttpRequest) -> Self {
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                let value = if request.is_sensitive(k) { REDACTED.to_string() } else { v.clone() };
+                (k.to_ascii_lowercase(), value)
+            })
+            .collect();
+        RecordedRequest {
+            method: request.method().as_str().to_string(),
+            path: url_path(request.url()),
+            headers,
+            body: request.body().unwrap_or_default().to_string(),
+        }
+    }
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn headers(&self) -> &BTreeMap<String, String> {
+        &self.headers
+    }
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+    fn matches(&self, other: &RecordedRequest) -> bool {
+        self.method.eq_ignore_ascii_case(&other.method)
+            && self.path == other.path
+            && normalize_body(&self.body) == normalize_body(&other.body)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedResponse {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    chunks: Vec<RecordedChunk>,
+    // 录制时响应体没有读到结尾
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    incomplete: bool,
+}
+
+impl RecordedResponse {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+    pub fn headers(&self) -> &BTreeMap<String, String> {
+        &self.headers
+    }
+    pub fn chunks(&self) -> &[RecordedChunk] {
+        &self.chunks
+    }
+    // 录制时响应体是否没有读到结尾
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete
+    }
+    // 录制到的响应体
+    pub fn body(&self) -> String {
+        self.chunks.iter().map(|c| c.data.as_str()).collect()
+    }
+}
+
+/// 响应体的一个 chunk，delay_ms 为距上一个 chunk（第一个 chunk 为距发出请求）的毫秒数
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedChunk {
+    delay_ms: u64,
+    data: String,
+}
+
+impl RecordedChunk {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+// 只取 url 中的路径，例如 https://api.deepseek.com/chat/completions -> /chat/completions
+fn url_path(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .map(|u| u.path().to_string())
+        .unwrap_or_else(|_| url.to_string())
+}
+
+// JSON 请求体解析后重新序列化，键按字母顺序排列；非 JSON 请求体去掉首尾空白
+fn normalize_body(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value) => value.to_string(),
+        Err(_) => body.trim().to_string(),
+    }
+}
+
+/// 录制传输层，把请求转发给内层传输层，并在每次响应读完后把 cassette 写回文件
+pub struct RecordTransport<T> {
+    inner: T,
+    recorder: Arc<Recorder>,
+}
+
+impl<T: Transport> RecordTransport<T> {
+    // cassette 写入 path，已有文件会被覆盖
+    pub fn new(inner: T, path: impl AsRef<Path>) -> Self {
+        RecordTransport {
+            inner,
+            recorder: Arc::new(Recorder {
+                path: path.as_ref().to_path_buf(),
+                cassette: Mutex::new(Cassette::default()),
+            }),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.recorder.path
+    }
+}
+
+impl<T: Transport> Transport for RecordTransport<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let recorded = RecordedRequest::new(&request);
+            let started = Instant::now();
+            let response = self.inner.send(request).await?;
+            let headers: BTreeMap<String, String> = response
+                .headers()
+                .iter()
+                // 逐跳请求头只对当前连接有效，不写入 cassette
+                .filter(|(k, _)| !k.eq_ignore_ascii_case("connection") && !k.eq_ignore_ascii_case("transfer-encoding"))
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+                .collect();
+            let mut recording = Recording {
+                recorder: self.recorder.clone(),
+                interaction: Some(Interaction {
+                    request: recorded,
+                    response: RecordedResponse {
+                        status: response.status(),
+                        headers,
+                        chunks: Vec::new(),
+                        incomplete: false,
+                    },
+                }),
+                last: started,
+                pending: Vec::new(),
+                finished: false,
+            };
+            Ok(response.map_body(|mut body| {
+                futures::stream::poll_fn(move |cx| {
+                    let item = futures::ready!(body.poll_next_unpin(cx));
+                    match &item {
+                        Some(Ok(bytes)) => recording.push(bytes),
+                        Some(Err(_)) => {}
+                        None => recording.finished = true,
+                    }
+                    Poll::Ready(item)
+                })
+                .boxed()
+            }))
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Recorder {
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl Recorder {
+    fn push(&self, interaction: Interaction) {
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(interaction);
+        // 录制失败不影响真实请求
+        let _ = cassette.save(&self.path);
+    }
+}
+
+// 一次正在录制的响应，drop 时（响应读完或被丢弃）写入 cassette
+struct Recording {
+    recorder: Arc<Recorder>,
+    interaction: Option<Interaction>,
+    last: Instant,
+    // 被 chunk 边界截断的 UTF-8 字节，留给下一个 chunk
+    pending: Vec<u8>,
+    // 响应体是否读到了结尾
+    finished: bool,
+}
+
+impl Recording {
+    fn push(&mut self, bytes: &[u8]) {
+        let now = Instant::now();
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        let rest = self.pending.split_off(valid);
+        let data = String::from_utf8(std::mem::replace(&mut self.pending, rest)).unwrap_or_default();
+        if let Some(interaction) = self.interaction.as_mut() {
+            interaction.response.chunks.push(RecordedChunk {
+                delay_ms: now.duration_since(self.last).as_millis() as u64,
+                data,
+            });
+        }
+        self.last = now;
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(mut interaction) = self.interaction.take() {
+            if !self.pending.is_empty() {
+                let data = String::from_utf8_lossy(&self.pending).into_owned();
+                interaction.response.chunks.push(RecordedChunk { delay_ms: 0, data });
+            }
+            // SSE 客户端收到 `data: [DONE]` 后就不再读取，此时也算读到了结尾
+            let done = interaction.response.body().trim_end().ends_with("data: [DONE]");
+            interaction.response.incomplete = !self.finished && !done;
+            self.recorder.push(interaction);
+        }
+    }
+}
+
+/// 回放传输层，不访问网络
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Vec<Interaction>,
+    // 每条录制记录是否已经回放过
+    used: Mutex<Vec<bool>>,
+    // 是否按录制时的间隔发送 chunk
+    timing: bool,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        ReplayTransport {
+            interactions: cassette.interactions,
+            used: Mutex::new(used),
+            timing: false,
+        }
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+    // 为 true 时按录制时的间隔发送 chunk
+    pub fn timing(mut self, timing: bool) -> Self {
+        self.timing = timing;
+        self
+    }
+    // 查找匹配的录制响应。相同的请求按录制顺序依次回放，全部用过之后重复最后一条。
+    fn find(&self, request: &RecordedRequest) -> Result<RecordedResponse, Error> {
+        let mut used = self.used.lock().unwrap();
+        let matched: Vec<usize> = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.request.matches(request))
+            .map(|(index, _)| index)
+            .collect();
+        let index = matched
+            .iter()
+            .find(|&&i| !used[i])
+            .or(matched.last())
+            .copied()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("cassette 中没有匹配的请求: {} {}", request.method, request.path),
+                )
+            })?;
+        used[index] = true;
+        Ok(self.interactions[index].response.clone())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let response = self.find(&RecordedRequest::new(&request))?;
+            let timing = self.timing;
+            let incomplete = response.incomplete.then(|| {
+                Err(Error::new(ErrorKind::UnexpectedEof, "录制时响应没有读到结尾"))
+            });
+            let body: ByteStream = futures::stream::iter(response.chunks)
+                .then(move |chunk| async move {
+                    if timing && chunk.delay_ms > 0 {
+                        tokio::time::sleep(chunk.delay()).await;
+                    }
+                    Ok(Bytes::from(chunk.data))
+                })
+                .chain(futures::stream::iter(incomplete))
+                .boxed();
+            let headers = response.headers.into_iter().collect();
+            Ok(HttpResponse::new(response.status, headers, body))
+        })
+    }
+}
@@ -0,0 +1,5 @@
+pub mod cassette;
+pub mod transport;
+
+pub use cassette::{Cassette, RecordTransport, ReplayTransport};
+pub use transport::*;
@@ -0,0 +1,337 @@
+//! # 传输层
+//! 客户端只通过 [`Transport`] 发送请求，默认使用 reqwest 实现。
+//! 可以自行实现该 trait 接入 hyper、代理或测试替身。
+use crate::cancel::CancelToken;
+use crate::timeout::{TimeoutError, TimeoutKind, Timeouts};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use std::fmt;
+use std::io::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 日志和 Debug 输出中会被脱敏的请求头（小写）
+pub const SENSITIVE_HEADERS: [&str; 3] = ["authorization", "x-api-key", "api-key"];
+
+/// 响应体字节流
+pub type ByteStream = BoxStream<'static, Result<Bytes, Error>>;
+
+/// 请求方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+/// HTTP 请求，`Debug` 输出中包含密钥的请求头会被脱敏，见 [`HttpRequest::is_sensitive`]
+#[derive(Clone)]
+pub struct HttpRequest {
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    // 通过 secret_header 添加的请求头名称（小写）
+    sensitive: Vec<String>,
+    body: Option<String>,
+    timeouts: Timeouts,
+    cancel: Option<CancelToken>,
+}
+
+impl HttpRequest {
+    pub fn get(url: &str) -> Self {
+        HttpRequest {
+            method: Method::Get,
+            url: url.to_string(),
+            headers: Vec::new(),
+            sensitive: Vec::new(),
+            body: None,
+            timeouts: Timeouts::default(),
+            cancel: None,
+        }
+    }
+    pub fn post(url: &str, body: String) -> Self {
+        HttpRequest {
+            method: Method::Post,
+            url: url.to_string(),
+            headers: Vec::new(),
+            sensitive: Vec::new(),
+            body: Some(body),
+            timeouts: Timeouts::default(),
+            cancel: None,
+        }
+    }
+    // 添加请求头，同名请求头会被替换
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.set_header(name, value);
+        self
+    }
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+    // 添加包含密钥的请求头，例如 provider 自定义的鉴权请求头；日志、Debug 输出和 cassette 中会被脱敏
+    pub fn secret_header(mut self, name: &str, value: &str) -> Self {
+        self.set_header(name, value);
+        let name = name.to_ascii_lowercase();
+        if !self.sensitive.contains(&name) {
+            self.sensitive.push(name);
+        }
+        self
+    }
+    // 请求头是否需要脱敏：[`SENSITIVE_HEADERS`] 中的名称，或者通过 secret_header 添加的请求头
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        SENSITIVE_HEADERS.contains(&name.as_str()) || self.sensitive.contains(&name)
+    }
+    pub fn method(&self) -> Method {
+        self.method
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+    // 按名称查找请求头，不区分大小写
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+    // 本次请求的超时，覆盖客户端上设置的默认值
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    pub fn get_timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+    // 取消令牌，见 [`crate::cancel`]
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+    pub fn get_cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
+}
+
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(k, v)| {
+                let v = if self.is_sensitive(k) { "[REDACTED]" } else { v.as_str() };
+                (k.as_str(), v)
+            })
+            .collect();
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body", &self.body)
+            .field("timeouts", &self.timeouts)
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
+
+/// HTTP 响应，响应体以字节流的形式给出
+pub struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: ByteStream,
+}
+
+impl fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: ByteStream) -> Self {
+        HttpResponse { status, headers, body }
+    }
+    // 响应体一次性给出
+    pub fn from_bytes(status: u16, headers: Vec<(String, String)>, body: impl Into<Bytes>) -> Self {
+        let body: Bytes = body.into();
+        Self::new(status, headers, futures::stream::once(async move { Ok(body) }).boxed())
+    }
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+    // 按名称查找响应头，不区分大小写
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    // `Retry-After` 响应头中的等待时间（秒）
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.header("retry-after")
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    }
+    pub fn into_body(self) -> ByteStream {
+        self.body
+    }
+    // 替换响应体，用于包装字节流
+    pub fn map_body(self, f: impl FnOnce(ByteStream) -> ByteStream) -> Self {
+        HttpResponse {
+            status: self.status,
+            headers: self.headers,
+            body: f(self.body),
+        }
+    }
+    // 读取完整的响应体
+    pub async fn bytes(self) -> Result<Bytes, Error> {
+        let chunks: Vec<Bytes> = self.body.try_collect().await?;
+        Ok(chunks.concat().into())
+    }
+    pub async fn text(self) -> Result<String, Error> {
+        let bytes = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// 传输层。实现应当按 `request.get_timeouts().get_connect()` 限制建立连接的时间，
+/// 超时时返回由 [`TimeoutError`] 转换的 `io::Error`；其他超时由客户端处理
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        (**self).send(request)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        (**self).send(request)
+    }
+}
+
+type ClientFactory = Arc<dyn Fn() -> reqwest::ClientBuilder + Send + Sync>;
+
+/// 基于 reqwest 的默认传输层。
+/// reqwest 只能在创建客户端时设置连接超时，所以每种连接超时使用一个单独的客户端（共享同一个 factory 的配置）
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    // 用于按连接超时创建客户端，为 None 时忽略请求上的连接超时
+    factory: Option<ClientFactory>,
+    clients: Arc<Mutex<HashMap<Duration, reqwest::Client>>>,
+}
+
+impl fmt::Debug for ReqwestTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReqwestTransport").field("client", &self.client).finish_non_exhaustive()
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        ReqwestTransport {
+            client: reqwest::Client::new(),
+            factory: Some(Arc::new(reqwest::Client::builder)),
+            clients: Arc::default(),
+        }
+    }
+    // 使用自定义的 reqwest 客户端，例如配置了代理或 TLS；请求上的连接超时会被忽略，
+    // 需要时在 reqwest 客户端上设置，或者使用 [`ReqwestTransport::from_builder`]
+    pub fn from_client(client: reqwest::Client) -> Self {
+        ReqwestTransport {
+            client,
+            factory: None,
+            clients: Arc::default(),
+        }
+    }
+    // 由 factory 返回的构建器创建客户端，例如配置了代理或 TLS；支持请求上的连接超时
+    pub fn from_builder(factory: impl Fn() -> reqwest::ClientBuilder + Send + Sync + 'static) -> Result<Self, Error> {
+        Ok(ReqwestTransport {
+            client: factory().build().map_err(Error::other)?,
+            factory: Some(Arc::new(factory)),
+            clients: Arc::default(),
+        })
+    }
+
+    fn client(&self, connect: Option<Duration>) -> Result<reqwest::Client, Error> {
+        let (Some(factory), Some(connect)) = (&self.factory, connect) else {
+            return Ok(self.client.clone());
+        };
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&connect) {
+            return Ok(client.clone());
+        }
+        let client = factory().connect_timeout(connect).build().map_err(Error::other)?;
+        clients.insert(connect, client.clone());
+        Ok(client)
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let method = match request.method {
+                Method::Get => reqwest::Method::GET,
+                Method::Post => reqwest::Method::POST,
+            };
+            let connect = request.timeouts.get_connect();
+            let mut builder = self.client(connect)?.request(method, &request.url);
+            for (name, value) in &request.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            let response = builder.send().await.map_err(|err| match connect {
+                Some(after) if err.is_connect() && err.is_timeout() => TimeoutError::new(TimeoutKind::Connect, after).into(),
+                _ => Error::other(err),
+            })?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+                .collect();
+            let body = response.bytes_stream().map_err(Error::other).boxed();
+            Ok(HttpResponse::new(status, headers, body))
+        })
+    }
+}
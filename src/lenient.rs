@@ -0,0 +1,106 @@
+//! # 宽松反序列化
+//! 接口返回的结构可能变化，响应类型对此是宽容的，不会因为结构变化而让整个调用失败：
+//!
+//! - 缺少或为 null 的字段使用缺省值（空字符串、0、空列表）
+//! - 未知的字段保存在各类型的 `extra` 中，序列化时原样输出
+//!
+//! 测试中可以用 [`check`] 或客户端的严格模式（`DeepSeekClient::strict`）发现这些变化，而不是等到线上出错。
+//!
+//! ```
+//! use deepseek_rs::chat::ChatResponse;
+//! use deepseek_rs::lenient::{self, Drift};
+//!
+//! let json = r#"{"id": "1", "choices": [], "created": 0, "model": "deepseek-chat",
+//!     "object": "chat.completion", "usage": null, "service_tier": "default"}"#;
+//! let (response, drift) = lenient::check::<ChatResponse>(json).unwrap();
+//! assert_eq!(response.extra()["service_tier"], "default");
+//! assert_eq!(drift, vec![Drift::Unknown(String::from("service_tier"))]);
+//! ```
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::cell::Cell;
+use std::fmt;
+
+/// 未知字段
+pub type Extra = serde_json::Map<String, Value>;
+
+thread_local! {
+    // 为 true 时序列化不输出未知字段，用于比较结构
+    static KNOWN_ONLY: Cell<bool> = const { Cell::new(false) };
+}
+
+// 为 null 时使用缺省值；缺少字段时由类型上的 #[serde(default)] 处理
+pub(crate) fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// 序列化时是否跳过未知字段
+pub(crate) fn skip_extra(extra: &Extra) -> bool {
+    extra.is_empty() || KNOWN_ONLY.with(Cell::get)
+}
+
+/// 响应结构与本库定义不一致的地方，路径形如 `choices[0].message.role`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    // 本库没有定义的字段
+    Unknown(String),
+    // 缺少的字段，使用了缺省值
+    Missing(String),
+    // 为 null 的字段，使用了缺省值
+    Null(String),
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Drift::Unknown(path) => write!(f, "未知字段 {}", path),
+            Drift::Missing(path) => write!(f, "缺少字段 {}", path),
+            Drift::Null(path) => write!(f, "字段 {} 为 null", path),
+        }
+    }
+}
+
+/// 解析 JSON，同时列出与类型定义不一致的地方。
+/// 可以省略的字段（例如 `usage`）缺少或为 null 时不算不一致
+pub fn check<T: DeserializeOwned + Serialize>(json: &str) -> Result<(T, Vec<Drift>), serde_json::Error> {
+    let original: Value = serde_json::from_str(json)?;
+    let value: T = serde_json::from_value(original.clone())?;
+    KNOWN_ONLY.with(|known| known.set(true));
+    let known = serde_json::to_value(&value);
+    KNOWN_ONLY.with(|known| known.set(false));
+    let mut drift = Vec::new();
+    diff(&original, &known?, "", &mut drift);
+    Ok((value, drift))
+}
+
+fn diff(original: &Value, known: &Value, path: &str, out: &mut Vec<Drift>) {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (original, known) {
+        (Value::Object(original), Value::Object(known)) => {
+            for (key, value) in original {
+                match known.get(key) {
+                    Some(Value::Null) | None if value.is_null() => {}
+                    None => out.push(Drift::Unknown(join(key))),
+                    Some(_) if value.is_null() => out.push(Drift::Null(join(key))),
+                    Some(known) => diff(value, known, &join(key), out),
+                }
+            }
+            for (key, value) in known {
+                if !value.is_null() && !original.contains_key(key) {
+                    out.push(Drift::Missing(join(key)));
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(known)) => {
+            for (i, (original, known)) in original.iter().zip(known).enumerate() {
+                diff(original, known, &format!("{}[{}]", path, i), out);
+            }
+        }
+        _ => {}
+    }
+}
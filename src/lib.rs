@@ -1,5 +1,39 @@
+pub mod auth;
 pub mod base_types;
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod cancel;
 pub mod chat;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod completions;
+#[cfg(feature = "client")]
+pub mod config;
+pub mod error;
+#[cfg(feature = "client")]
 pub mod http;
+pub mod lenient;
+#[cfg(feature = "client")]
+pub mod middleware;
 pub mod model;
-pub mod user;
\ No newline at end of file
+pub mod prompt;
+pub mod provider;
+#[cfg(feature = "client")]
+pub mod rate_limit;
+pub mod retry;
+pub mod sse;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timeout;
+#[cfg(feature = "decimal")]
+pub mod user;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "client")]
+pub use client::DeepSeekClient;
+pub use error::DeepSeekError;
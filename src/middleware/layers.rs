@@ -0,0 +1,238 @@
+//! # 内置中间件
+use super::{Middleware, Next, RequestContext};
+use crate::http::{HttpRequest, HttpResponse};
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use std::io::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 请求日志，Authorization 等请求头会被脱敏，每行日志交给调用方提供的 sink 输出，
+/// 例如 `|line| log::info!("{}", line)`
+pub struct LoggingLayer {
+    sink: Arc<dyn Fn(&str) + Send + Sync>,
+    // 是否记录请求体
+    body: bool,
+}
+
+impl LoggingLayer {
+    pub fn new(sink: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        LoggingLayer {
+            sink: Arc::new(sink),
+            body: false,
+        }
+    }
+    // 是否记录请求体，默认不记录
+    pub fn body(mut self, body: bool) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+impl Middleware for LoggingLayer {
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let ctx = next.context();
+            let headers: Vec<String> = request
+                .headers()
+                .iter()
+                .map(|(k, v)| {
+                    if request.is_sensitive(k) {
+                        format!("{}: [REDACTED]", k)
+                    } else {
+                        format!("{}: {}", k, v)
+                    }
+                })
+                .collect();
+            let mut line = format!(
+                "--> {} {} request_id={} headers=[{}]",
+                request.method().as_str(),
+                request.url(),
+                ctx.request_id(),
+                headers.join(", ")
+            );
+            if self.body {
+                line.push_str(&format!(" body={}", request.body().unwrap_or_default()));
+            }
+            (self.sink)(&line);
+            let started = Instant::now();
+            let result = next.run(request).await;
+            match &result {
+                Ok(response) => (self.sink)(&format!(
+                    "<-- {} {} request_id={} in {:?}",
+                    response.status(),
+                    ctx.endpoint(),
+                    ctx.request_id(),
+                    started.elapsed()
+                )),
+                Err(err) => (self.sink)(&format!(
+                    "<-- error {} request_id={} in {:?}: {}",
+                    ctx.endpoint(),
+                    ctx.request_id(),
+                    started.elapsed(),
+                    err
+                )),
+            }
+            result
+        })
+    }
+}
+
+/// 一次调用的耗时
+#[derive(Debug, Clone)]
+pub struct Timing {
+    pub request_id: String,
+    pub endpoint: String,
+    pub model: Option<String>,
+    // 响应状态码，请求失败时为 None
+    pub status: Option<u16>,
+    // 发出请求到收到响应头的时间
+    pub time_to_headers: Duration,
+    // 发出请求到响应体读完（或被丢弃）的时间
+    pub total: Duration,
+}
+
+/// 计时中间件，每次调用结束（响应体读完或被丢弃）后调用回调一次
+pub struct TimingLayer {
+    callback: Arc<dyn Fn(Timing) + Send + Sync>,
+}
+
+impl TimingLayer {
+    pub fn new(callback: impl Fn(Timing) + Send + Sync + 'static) -> Self {
+        TimingLayer {
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+// 响应体被丢弃时调用计时回调
+struct TimingGuard {
+    timing: Option<Timing>,
+    started: Instant,
+    callback: Arc<dyn Fn(Timing) + Send + Sync>,
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        if let Some(mut timing) = self.timing.take() {
+            timing.total = self.started.elapsed();
+            (self.callback)(timing);
+        }
+    }
+}
+
+impl Middleware for TimingLayer {
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let ctx: &RequestContext = next.context();
+            let started = Instant::now();
+            let result = next.run(request).await;
+            let mut timing = Timing {
+                request_id: ctx.request_id().to_string(),
+                endpoint: ctx.endpoint().to_string(),
+                model: ctx.model().map(String::from),
+                status: None,
+                time_to_headers: started.elapsed(),
+                total: Duration::ZERO,
+            };
+            let response = match result {
+                Ok(response) => response,
+                Err(err) => {
+                    timing.total = timing.time_to_headers;
+                    (self.callback)(timing);
+                    return Err(err);
+                }
+            };
+            timing.status = Some(response.status());
+            let guard = TimingGuard {
+                timing: Some(timing),
+                started,
+                callback: self.callback.clone(),
+            };
+            Ok(response.map_body(move |body| {
+                body.map(move |chunk| {
+                    let _ = &guard;
+                    chunk
+                })
+                .boxed()
+            }))
+        })
+    }
+}
+
+/// 为每个请求添加固定的请求头，同名请求头会被替换，
+/// 例如按租户注入 `Authorization`
+#[derive(Debug, Clone, Default)]
+pub struct HeaderLayer {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl Middleware for HeaderLayer {
+    fn handle<'a>(&'a self, mut request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        for (name, value) in &self.headers {
+            request.set_header(name, value);
+        }
+        next.run(request)
+    }
+}
+
+/// 请求 ID 默认使用的请求头
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// 把请求 ID 写入请求头。默认使用客户端为每次调用生成的 ID，
+/// 也可以通过 [`RequestIdLayer::from_fn`] 传递上游服务的请求 ID。请求中已有该请求头时不会覆盖。
+pub struct RequestIdLayer {
+    header: String,
+    generator: Option<Arc<dyn Fn() -> Option<String> + Send + Sync>>,
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIdLayer {
+    pub fn new() -> Self {
+        RequestIdLayer {
+            header: String::from(REQUEST_ID_HEADER),
+            generator: None,
+        }
+    }
+    // 由回调提供请求 ID，返回 None 时使用客户端生成的 ID
+    pub fn from_fn(generator: impl Fn() -> Option<String> + Send + Sync + 'static) -> Self {
+        RequestIdLayer {
+            header: String::from(REQUEST_ID_HEADER),
+            generator: Some(Arc::new(generator)),
+        }
+    }
+    // 修改请求头名称
+    pub fn header_name(mut self, name: &str) -> Self {
+        self.header = name.to_string();
+        self
+    }
+}
+
+impl Middleware for RequestIdLayer {
+    fn handle<'a>(&'a self, mut request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        if request.header_value(&self.header).is_none() {
+            let id = self
+                .generator
+                .as_ref()
+                .and_then(|g| g())
+                .unwrap_or_else(|| next.context().request_id().to_string());
+            request.set_header(&self.header, &id);
+        }
+        next.run(request)
+    }
+}
@@ -0,0 +1,131 @@
+//! # 指标
+//! 需要开启 `metrics` 特性。通过 [`metrics`](https://docs.rs/metrics) 门面记录指标，
+//! 配合 metrics-exporter-prometheus 等导出器即可得到 Prometheus 指标：
+//!
+//! | 指标 | 类型 | 标签 |
+//! | --- | --- | --- |
+//! | `deepseek_requests_total` | counter | model, endpoint, status |
+//! | `deepseek_retries_total` | counter | model, endpoint |
+//! | `deepseek_request_duration_seconds` | histogram | model, endpoint |
+//! | `deepseek_prompt_tokens_total` | counter | model, cache（hit / miss） |
+//! | `deepseek_completion_tokens_total` | counter | model |
+//! | `deepseek_request_cost` | histogram | model |
+//! | `deepseek_time_to_first_token_seconds` | histogram | model |
+//! | `deepseek_stream_total_duration_seconds` | histogram | model |
+//!
+//! `deepseek_request_duration_seconds` 为单次尝试从发出请求到收到响应头的时间，重试时每次尝试分别记录，
+//! 不包括之前的尝试和退避等待；请求失败时 status 为 `error`。
+//! `deepseek_time_to_first_token_seconds` 和 `deepseek_stream_total_duration_seconds` 从调用开始计时，
+//! 包括重试和退避等待，是调用方看到的端到端时间。
+//! FIM 接口同样记录 token 数和费用，流式 FIM 的用量在最后一个 chunk 中。
+use super::{Middleware, Next, RequestContext};
+use crate::base_types::data::Pricing;
+use crate::chat::{ChatCompletionChunk, ChatResponse, Usage};
+use crate::completions::FimResponse;
+use crate::http::{HttpRequest, HttpResponse};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::io::Error;
+use std::time::Instant;
+
+/// 指标中间件
+#[derive(Debug, Clone, Default)]
+pub struct MetricsLayer {
+    // 按模型设置的价格
+    pricing: HashMap<String, Pricing>,
+    default_pricing: Pricing,
+}
+
+impl MetricsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // 设置某个模型的价格
+    pub fn pricing(mut self, model: &str, pricing: Pricing) -> Self {
+        self.pricing.insert(model.to_string(), pricing);
+        self
+    }
+    // 设置没有单独配置价格的模型使用的价格
+    pub fn default_pricing(mut self, pricing: Pricing) -> Self {
+        self.default_pricing = pricing;
+        self
+    }
+
+    fn record_usage(&self, model: &str, usage: &Usage) {
+        let model = model.to_string();
+        ::metrics::counter!("deepseek_prompt_tokens_total", "model" => model.clone(), "cache" => "hit")
+            .increment(usage.prompt_cache_hit_tokens().max(0) as u64);
+        ::metrics::counter!("deepseek_prompt_tokens_total", "model" => model.clone(), "cache" => "miss")
+            .increment(usage.prompt_cache_miss_tokens().max(0) as u64);
+        ::metrics::counter!("deepseek_completion_tokens_total", "model" => model.clone())
+            .increment(usage.completion_tokens().max(0) as u64);
+        let pricing = self.pricing.get(&model).unwrap_or(&self.default_pricing);
+        ::metrics::histogram!("deepseek_request_cost", "model" => model).record(usage.cost(pricing));
+    }
+}
+
+fn model_label(ctx: &RequestContext) -> String {
+    ctx.model().unwrap_or_default().to_string()
+}
+
+impl Middleware for MetricsLayer {
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let ctx = next.context();
+            let model = model_label(ctx);
+            let endpoint = ctx.endpoint().to_string();
+            if ctx.attempt() > 0 {
+                ::metrics::counter!("deepseek_retries_total", "model" => model.clone(), "endpoint" => endpoint.clone())
+                    .increment(1);
+            }
+            let started = Instant::now();
+            let result = next.run(request).await;
+            let status = match &result {
+                Ok(response) => response.status().to_string(),
+                Err(_) => String::from("error"),
+            };
+            ::metrics::counter!(
+                "deepseek_requests_total",
+                "model" => model.clone(),
+                "endpoint" => endpoint.clone(),
+                "status" => status
+            )
+            .increment(1);
+            ::metrics::histogram!("deepseek_request_duration_seconds", "model" => model, "endpoint" => endpoint)
+                .record(started.elapsed().as_secs_f64());
+            result
+        })
+    }
+
+    fn on_chat_response(&self, response: &mut ChatResponse, ctx: &RequestContext) {
+        if let Some(usage) = response.usage() {
+            self.record_usage(&model_label(ctx), usage);
+        }
+    }
+
+    fn on_chunk(&self, chunk: &mut ChatCompletionChunk, ctx: &RequestContext) {
+        let finished = chunk.choices().iter().any(|c| c.finish_reason().is_some());
+        if !finished && chunk.usage().is_none() {
+            return;
+        }
+        let model = model_label(ctx);
+        if let Some(usage) = chunk.usage() {
+            self.record_usage(&model, usage);
+        }
+        if !finished {
+            return;
+        }
+        if let Some(ttft) = ctx.time_to_first_token() {
+            ::metrics::histogram!("deepseek_time_to_first_token_seconds", "model" => model.clone())
+                .record(ttft.as_secs_f64());
+        }
+        ::metrics::histogram!("deepseek_stream_total_duration_seconds", "model" => model)
+            .record(ctx.started().elapsed().as_secs_f64());
+    }
+
+    fn on_fim_response(&self, response: &mut FimResponse, ctx: &RequestContext) {
+        if let Some(usage) = response.usage() {
+            self.record_usage(&model_label(ctx), usage);
+        }
+    }
+}
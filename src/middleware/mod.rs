@@ -0,0 +1,184 @@
+//! # 中间件
+//! 客户端上的每个中间件可以在请求发出前修改 `ChatRequest` 和 HTTP 请求，
+//! 包裹 HTTP 调用本身（类似 tower 的 layer），并检查或修改响应和流式 chunk。
+//! 中间件按添加的顺序执行，先添加的在最外层。
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::middleware::{HeaderLayer, LoggingLayer, RequestIdLayer, TimingLayer};
+//!
+//! let client = DeepSeekClient::new("sk-...")
+//!     .layer(RequestIdLayer::new())
+//!     .layer(HeaderLayer::new().header("X-Tenant", "team-a"))
+//!     .layer(LoggingLayer::new(|line| eprintln!("{}", line)))
+//!     .layer(TimingLayer::new(|timing| println!("{:?}", timing)));
+//! ```
+pub mod layers;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "tracing")]
+pub mod trace;
+
+pub use layers::*;
+#[cfg(feature = "metrics")]
+pub use self::metrics::MetricsLayer;
+#[cfg(feature = "tracing")]
+pub use trace::TracingLayer;
+
+use crate::chat::{ChatCompletionChunk, ChatRequest, ChatResponse};
+use crate::completions::FimResponse;
+use crate::http::{HttpRequest, HttpResponse, Transport};
+use futures::future::BoxFuture;
+use std::io::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 一次接口调用的上下文，在该次调用的所有中间件之间共享
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    request_id: String,
+    endpoint: String,
+    model: Option<String>,
+    attempt: u32,
+    started: Instant,
+    // 流式响应中第一个包含内容的 chunk 到达的时间
+    first_token: Arc<OnceLock<Duration>>,
+    #[cfg(feature = "tracing")]
+    span: ::tracing::Span,
+}
+
+impl RequestContext {
+    pub fn new(endpoint: &str, model: Option<&str>) -> Self {
+        let request_id = generate_request_id();
+        #[cfg(feature = "tracing")]
+        let span = ::tracing::info_span!(
+            "deepseek.request",
+            endpoint,
+            model = ::tracing::field::Empty,
+            request_id = request_id.as_str(),
+            attempt = 0u32,
+            status = ::tracing::field::Empty,
+            latency_ms = ::tracing::field::Empty,
+            ttft_ms = ::tracing::field::Empty,
+            prompt_tokens = ::tracing::field::Empty,
+            prompt_cache_hit_tokens = ::tracing::field::Empty,
+            completion_tokens = ::tracing::field::Empty,
+            total_tokens = ::tracing::field::Empty,
+            error = ::tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        if let Some(model) = model {
+            span.record("model", model);
+        }
+        RequestContext {
+            request_id,
+            endpoint: endpoint.to_string(),
+            model: model.map(String::from),
+            attempt: 0,
+            started: Instant::now(),
+            first_token: Arc::new(OnceLock::new()),
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+    // 设置重试次数
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        #[cfg(feature = "tracing")]
+        self.span.record("attempt", attempt);
+        self
+    }
+    // 客户端为本次调用生成的 ID
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+    // 接口路径，例如 /chat/completions
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+    // 重试次数，第一次请求为 0
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+    // 本次调用开始的时间
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+    // 流式响应的首 token 时间，还没有收到内容时为 None
+    pub fn time_to_first_token(&self) -> Option<Duration> {
+        self.first_token.get().copied()
+    }
+    pub(crate) fn mark_first_token(&self) {
+        if self.first_token.get().is_none() {
+            let _ = self.first_token.set(self.started.elapsed());
+        }
+    }
+    // 本次调用的 span，需要开启 `tracing` 特性
+    #[cfg(feature = "tracing")]
+    pub fn span(&self) -> &::tracing::Span {
+        &self.span
+    }
+}
+
+fn generate_request_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:016x}-{:04x}-{:04x}", nanos, std::process::id() & 0xffff, count & 0xffff)
+}
+
+/// 中间件，所有方法都有默认实现，只需实现关心的部分
+pub trait Middleware: Send + Sync {
+    // 修改将要发送的 chat 请求
+    fn on_chat_request(&self, _request: &mut ChatRequest, _ctx: &RequestContext) {}
+    // 包裹 HTTP 调用，可以修改请求、计时、替换响应；必须调用 next.run 才会继续
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        next.run(request)
+    }
+    // 检查或修改解析后的 chat 响应
+    fn on_chat_response(&self, _response: &mut ChatResponse, _ctx: &RequestContext) {}
+    // 检查或修改流式响应的每个 chunk
+    fn on_chunk(&self, _chunk: &mut ChatCompletionChunk, _ctx: &RequestContext) {}
+    // 检查或修改 FIM 响应，流式 FIM 的每个 chunk 也会调用
+    fn on_fim_response(&self, _response: &mut FimResponse, _ctx: &RequestContext) {}
+}
+
+/// 剩余的中间件和最终的传输层
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn Transport,
+    ctx: &'a RequestContext,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(middlewares: &'a [Arc<dyn Middleware>], transport: &'a dyn Transport, ctx: &'a RequestContext) -> Self {
+        Next {
+            middlewares,
+            transport,
+            ctx,
+        }
+    }
+    pub fn context(&self) -> &'a RequestContext {
+        self.ctx
+    }
+    // 交给下一个中间件，没有中间件时由传输层发送
+    pub fn run(self, request: HttpRequest) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(
+                request,
+                Next {
+                    middlewares: rest,
+                    ..self
+                },
+            ),
+            None => self.transport.send(request),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! # tracing 埋点
+//! 需要开启 `tracing` 特性。每次接口调用对应一个 `deepseek.request` span，记录模型、接口、请求 ID、
+//! 重试次数、状态码、总耗时、流式响应的首 token 时间以及 `Usage` 中的 token 数，chat 与 FIM 接口相同。
+//! 请求与响应内容只在 trace 级别输出，并且可以脱敏。
+use super::{Middleware, Next, RequestContext};
+use crate::chat::{ChatCompletionChunk, ChatRequest, ChatResponse, Usage};
+use crate::completions::FimResponse;
+use crate::http::{HttpRequest, HttpResponse};
+use futures::future::BoxFuture;
+use std::io::Error;
+use std::sync::Arc;
+use tracing::Instrument;
+
+// 脱敏函数
+type Redactor = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// tracing 埋点中间件
+pub struct TracingLayer {
+    // 是否在 trace 级别输出请求与响应内容
+    bodies: bool,
+    redact: Option<Redactor>,
+}
+
+impl Default for TracingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TracingLayer {
+    pub fn new() -> Self {
+        TracingLayer {
+            bodies: true,
+            redact: None,
+        }
+    }
+    // 是否在 trace 级别输出请求与响应内容，默认输出
+    pub fn bodies(mut self, bodies: bool) -> Self {
+        self.bodies = bodies;
+        self
+    }
+    // 输出请求与响应内容前先经过 redact 处理
+    pub fn redact(mut self, redact: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.redact = Some(Arc::new(redact));
+        self
+    }
+
+    fn trace_body(&self, ctx: &RequestContext, kind: &str, body: &str) {
+        if !self.bodies || !tracing::enabled!(tracing::Level::TRACE) {
+            return;
+        }
+        let body = match &self.redact {
+            Some(redact) => redact(body),
+            None => body.to_string(),
+        };
+        tracing::trace!(parent: ctx.span(), kind, body = body.as_str(), "deepseek body");
+    }
+}
+
+fn record_usage(ctx: &RequestContext, usage: &Usage) {
+    let span = ctx.span();
+    span.record("prompt_tokens", usage.prompt_tokens());
+    span.record("prompt_cache_hit_tokens", usage.prompt_cache_hit_tokens());
+    span.record("completion_tokens", usage.completion_tokens());
+    span.record("total_tokens", usage.total_tokens());
+}
+
+impl Middleware for TracingLayer {
+    fn on_chat_request(&self, request: &mut ChatRequest, ctx: &RequestContext) {
+        if let Ok(json) = request.to_json() {
+            self.trace_body(ctx, "request", &json);
+        }
+    }
+
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        let ctx = next.context();
+        let span = ctx.span().clone();
+        Box::pin(
+            async move {
+                let result = next.run(request).await;
+                let span = ctx.span();
+                // chat 接口在响应读完后会用总耗时覆盖
+                span.record("latency_ms", ctx.started().elapsed().as_millis() as u64);
+                match &result {
+                    Ok(response) => {
+                        span.record("status", response.status());
+                        if !response.is_success() {
+                            span.record("error", format!("HTTP {}", response.status()).as_str());
+                            tracing::warn!(status = response.status(), "deepseek request failed");
+                        }
+                    }
+                    Err(err) => {
+                        span.record("error", err.to_string().as_str());
+                        tracing::warn!(error = %err, "deepseek request failed");
+                    }
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn on_chat_response(&self, response: &mut ChatResponse, ctx: &RequestContext) {
+        let latency = ctx.started().elapsed();
+        ctx.span().record("latency_ms", latency.as_millis() as u64);
+        if let Some(usage) = response.usage() {
+            record_usage(ctx, usage);
+        }
+        if let Ok(json) = serde_json::to_string(response) {
+            self.trace_body(ctx, "response", &json);
+        }
+        tracing::debug!(parent: ctx.span(), latency_ms = latency.as_millis() as u64, "deepseek request completed");
+    }
+
+    fn on_chunk(&self, chunk: &mut ChatCompletionChunk, ctx: &RequestContext) {
+        if let Ok(json) = serde_json::to_string(chunk) {
+            self.trace_body(ctx, "chunk", &json);
+        }
+        let finished = chunk.choices().iter().any(|c| c.finish_reason().is_some());
+        if !finished && chunk.usage().is_none() {
+            return;
+        }
+        let span = ctx.span();
+        let latency = ctx.started().elapsed();
+        span.record("latency_ms", latency.as_millis() as u64);
+        if let Some(ttft) = ctx.time_to_first_token() {
+            span.record("ttft_ms", ttft.as_millis() as u64);
+        }
+        if let Some(usage) = chunk.usage() {
+            record_usage(ctx, usage);
+        }
+        tracing::debug!(parent: span, latency_ms = latency.as_millis() as u64, "deepseek stream completed");
+    }
+
+    // FIM 完整响应和流式响应的每个 chunk 都会调用，只在带有 finish_reason 或 usage 时记录结果
+    fn on_fim_response(&self, response: &mut FimResponse, ctx: &RequestContext) {
+        if let Ok(json) = serde_json::to_string(response) {
+            self.trace_body(ctx, "response", &json);
+        }
+        let finished = response.choices().iter().any(|c| c.finish_reason().is_some());
+        if !finished && response.usage().is_none() {
+            return;
+        }
+        let span = ctx.span();
+        let latency = ctx.started().elapsed();
+        span.record("latency_ms", latency.as_millis() as u64);
+        if let Some(usage) = response.usage() {
+            record_usage(ctx, usage);
+        }
+        tracing::debug!(parent: span, latency_ms = latency.as_millis() as u64, "deepseek request completed");
+    }
+}
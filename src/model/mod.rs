@@ -2,4 +2,4 @@ pub mod request;
 pub mod response;
 
 pub use request::ModelRequest;
-pub use response::ModelResponse;
\ No newline at end of file
+pub use response::{ModelInfo, ModelResponse};
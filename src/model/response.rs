@@ -1,35 +1,86 @@
+use crate::base_types::data::ModelName;
+use crate::lenient::{null_default, skip_extra, Extra};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 可用模型列表
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ModelResponse {
+    #[serde(deserialize_with = "null_default")]
     object: String,
-    data: Vec<Data>,
+    #[serde(deserialize_with = "null_default")]
+    data: Vec<ModelInfo>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
 impl ModelResponse {
     pub fn object(&self) -> &str {
         &self.object
     }
-    pub fn data(&self) -> &[Data] {
+    pub fn data(&self) -> &[ModelInfo] {
         &self.data
     }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 按 id 查找模型
+    pub fn get(&self, id: &str) -> Option<&ModelInfo> {
+        self.data.iter().find(|m| m.id == id)
+    }
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+    pub fn ids(&self) -> Vec<&str> {
+        self.data.iter().map(|m| m.id()).collect()
+    }
+    // 列表中已知的模型，未知的 id 会被忽略
+    pub fn names(&self) -> Vec<ModelName> {
+        self.data.iter().filter_map(|m| m.name()).collect()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Data {
+/// 模型信息
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelInfo {
+    // 模型的 id，例如 deepseek-chat
+    #[serde(deserialize_with = "null_default")]
     id: String,
+    // 对象的类型，其值为 model
+    #[serde(deserialize_with = "null_default")]
     object: String,
+    // 拥有该模型的组织
+    #[serde(deserialize_with = "null_default")]
     owned_by: String,
+    // 本库没有定义的字段，例如其他服务返回的 created
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-impl Data {
+impl ModelInfo {
     pub fn id(&self) -> &str {
         &self.id
     }
     pub fn object(&self) -> &str {
         &self.object
     }
+    pub fn owned_by(&self) -> &str {
+        &self.owned_by
+    }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    #[deprecated(note = "拼写错误，请使用 owned_by")]
     pub fn onwed_by(&self) -> &str {
         &self.owned_by
     }
-}
\ No newline at end of file
+    // 对应的 ModelName，本库不认识的模型为 None
+    pub fn name(&self) -> Option<ModelName> {
+        self.id.parse().ok()
+    }
+}
+
+#[deprecated(note = "请使用 ModelInfo")]
+pub type Data = ModelInfo;
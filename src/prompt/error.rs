@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// 提示词模板错误
+#[derive(Debug)]
+pub enum PromptError {
+    // 模板语法错误，line 为出错的行号
+    Parse { line: usize, message: String },
+    // 声明了角色的模板中，角色段落之外出现了内容
+    ContentOutsideRole,
+    // 消息内容模板（见 [`crate::chat::RequestTemplate`]）中声明了角色
+    RoleNotAllowed,
+    // 渲染时缺少的变量
+    MissingVariables(Vec<String>),
+    // 变量类型与用法不符
+    TypeMismatch { name: String, expected: &'static str },
+    // 读取模板文件失败
+    Io(std::io::Error),
+}
+
+impl PromptError {
+    pub(crate) fn parse(line: usize, message: impl Into<String>) -> Self {
+        PromptError::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PromptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromptError::Parse { line, message } => write!(f, "模板第 {} 行: {}", line, message),
+            PromptError::ContentOutsideRole => write!(f, "声明了角色的模板中，角色段落之外不能有内容"),
+            PromptError::RoleNotAllowed => write!(f, "消息内容模板中不能声明角色"),
+            PromptError::MissingVariables(names) => write!(f, "缺少模板变量: {}", names.join(", ")),
+            PromptError::TypeMismatch { name, expected } => write!(f, "变量 {} 应为{}", name, expected),
+            PromptError::Io(err) => write!(f, "读取模板失败: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PromptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PromptError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PromptError {
+    fn from(err: std::io::Error) -> Self {
+        PromptError::Io(err)
+    }
+}
@@ -0,0 +1,25 @@
+//! # 提示词模板
+//! 支持变量、条件段落、循环和角色声明，加载时检查语法，渲染前检查变量是否齐全，
+//! 直接渲染为 `Vec<Message>`。
+//!
+//! ```text
+//! {{#system}}
+//! 你是一名{{language}}翻译。
+//! {{/system}}
+//! {{#each examples}}
+//! {{#user}}{{this.source}}{{/user}}
+//! {{#assistant}}{{this.target}}{{/assistant}}
+//! {{/each}}
+//! {{#user}}
+//! {{#if context}}参考资料：{{context}}
+//! {{/if}}
+//! {{text}}
+//! {{/user}}
+//! ```
+pub mod error;
+pub mod parser;
+pub mod template;
+
+pub use error::PromptError;
+pub use parser::Role;
+pub use template::*;
@@ -0,0 +1,427 @@
+//! # 模板解析
+//! 语法：
+//! - `{{name}}`、`{{user.name}}`：变量
+//! - `{{#if name}}...{{else}}...{{/if}}`：条件段落
+//! - `{{#each items}}...{{/each}}`：循环，循环体内用 `{{this}}` 或 `{{this.field}}` 访问当前元素，
+//!   也可以写成 `{{#each items as item}}` 并用 `{{item}}` 访问
+//! - `{{#system}}`、`{{#user}}`、`{{#assistant}}`：声明消息角色
+//! - `\{{`：输出字面量 `{{`
+//!
+//! 只包含块标签的行在渲染时会整行去掉，方便把标签单独写在一行。
+use super::error::PromptError;
+
+/// 消息角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+    fn parse(name: &str) -> Option<Role> {
+        match name {
+            "system" => Some(Role::System),
+            "user" => Some(Role::User),
+            "assistant" => Some(Role::Assistant),
+            _ => None,
+        }
+    }
+}
+
+/// 语法树节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text(String),
+    Var(Vec<String>),
+    If {
+        cond: Vec<String>,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        list: Vec<String>,
+        binding: String,
+        body: Vec<Node>,
+    },
+    Role {
+        role: Role,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Var(String),
+    Open(String, String),
+    Else,
+    Close(String),
+}
+
+// 带行号的 token
+type Spanned = (Token, usize);
+
+pub fn parse(source: &str) -> Result<Vec<Node>, PromptError> {
+    let tokens = tokenize(source)?;
+    let mut iter = tokens.into_iter().peekable();
+    let (nodes, _) = parse_nodes(&mut iter, None, false)?;
+    Ok(nodes)
+}
+
+fn tokenize(source: &str) -> Result<Vec<Spanned>, PromptError> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let line_no = index + 1;
+        let mut line_tokens = tokenize_line(line, line_no)?;
+        // 只包含块标签的行，去掉空白和换行
+        let standalone = line_tokens.iter().any(|(t, _)| is_block(t))
+            && line_tokens.iter().all(|(t, _)| match t {
+                Token::Text(text) => text.trim().is_empty(),
+                other => is_block(other),
+            });
+        if standalone {
+            line_tokens.retain(|(t, _)| is_block(t));
+        }
+        tokens.extend(line_tokens);
+    }
+    Ok(tokens)
+}
+
+fn is_block(token: &Token) -> bool {
+    matches!(token, Token::Open(..) | Token::Else | Token::Close(_))
+}
+
+fn tokenize_line(line: &str, line_no: usize) -> Result<Vec<Spanned>, PromptError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        // `\{{` 转义为字面量
+        if rest[..start].ends_with('\\') {
+            text.push_str(&rest[..start - 1]);
+            text.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| PromptError::parse(line_no, "`{{` 没有闭合"))?;
+        if !text.is_empty() {
+            tokens.push((Token::Text(std::mem::take(&mut text)), line_no));
+        }
+        tokens.push((tag(after[..end].trim(), line_no)?, line_no));
+        rest = &after[end + 2..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push((Token::Text(text), line_no));
+    }
+    Ok(tokens)
+}
+
+fn tag(inner: &str, line_no: usize) -> Result<Token, PromptError> {
+    if inner.is_empty() {
+        return Err(PromptError::parse(line_no, "空的标签 `{{}}`"));
+    }
+    if let Some(open) = inner.strip_prefix('#') {
+        let mut parts = open.splitn(2, char::is_whitespace);
+        let keyword = parts.next().unwrap_or_default().to_string();
+        let args = parts.next().unwrap_or_default().trim().to_string();
+        return Ok(Token::Open(keyword, args));
+    }
+    if let Some(close) = inner.strip_prefix('/') {
+        return Ok(Token::Close(close.trim().to_string()));
+    }
+    if inner == "else" {
+        return Ok(Token::Else);
+    }
+    check_path(inner, line_no)?;
+    Ok(Token::Var(inner.to_string()))
+}
+
+fn check_path(path: &str, line_no: usize) -> Result<(), PromptError> {
+    let valid = path.split('.').all(|seg| {
+        !seg.is_empty() && seg.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(PromptError::parse(line_no, format!("非法的变量名 `{}`", path)))
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('.').map(String::from).collect()
+}
+
+fn parse_nodes<I>(
+    iter: &mut std::iter::Peekable<I>,
+    closing: Option<(&str, usize)>,
+    in_role: bool,
+) -> Result<(Vec<Node>, bool), PromptError>
+where
+    I: Iterator<Item = Spanned>,
+{
+    let mut nodes = Vec::new();
+    while let Some((token, line_no)) = iter.peek().cloned() {
+        match token {
+            Token::Close(name) => {
+                return match closing {
+                    Some((expect, _)) if expect == name => {
+                        iter.next();
+                        Ok((nodes, false))
+                    }
+                    Some((expect, _)) => Err(PromptError::parse(
+                        line_no,
+                        format!("期望 `{{{{/{}}}}}`，实际是 `{{{{/{}}}}}`", expect, name),
+                    )),
+                    None => Err(PromptError::parse(line_no, format!("多余的 `{{{{/{}}}}}`", name))),
+                };
+            }
+            Token::Else => {
+                // 返回 true 表示遇到了 else，由 if 继续解析 else 分支
+                if matches!(closing, Some(("if", _))) {
+                    iter.next();
+                    return Ok((nodes, true));
+                }
+                return Err(PromptError::parse(line_no, "`{{else}}` 只能出现在 `{{#if}}` 中"));
+            }
+            Token::Text(text) => {
+                iter.next();
+                nodes.push(Node::Text(text));
+            }
+            Token::Var(path) => {
+                iter.next();
+                nodes.push(Node::Var(split_path(&path)));
+            }
+            Token::Open(keyword, args) => {
+                iter.next();
+                nodes.push(parse_block(iter, &keyword, &args, line_no, in_role)?);
+            }
+        }
+    }
+    match closing {
+        Some((expect, opened_at)) => Err(PromptError::parse(
+            opened_at,
+            format!("`{{{{#{}}}}}` 没有对应的 `{{{{/{}}}}}`", expect, expect),
+        )),
+        None => Ok((nodes, false)),
+    }
+}
+
+fn parse_block<I>(
+    iter: &mut std::iter::Peekable<I>,
+    keyword: &str,
+    args: &str,
+    line_no: usize,
+    in_role: bool,
+) -> Result<Node, PromptError>
+where
+    I: Iterator<Item = Spanned>,
+{
+    match keyword {
+        "if" => {
+            check_path(args, line_no)?;
+            let (then, has_else) = parse_nodes(iter, Some(("if", line_no)), in_role)?;
+            let mut otherwise = Vec::new();
+            if has_else {
+                let (nodes, again) = parse_nodes(iter, Some(("if", line_no)), in_role)?;
+                if again {
+                    return Err(PromptError::parse(line_no, "`{{#if}}` 中只能有一个 `{{else}}`"));
+                }
+                otherwise = nodes;
+            }
+            Ok(Node::If {
+                cond: split_path(args),
+                then,
+                otherwise,
+            })
+        }
+        "each" => {
+            let mut parts = args.split_whitespace();
+            let list = parts.next().unwrap_or_default();
+            check_path(list, line_no)?;
+            let binding = match (parts.next(), parts.next(), parts.next()) {
+                (None, _, _) => String::from("this"),
+                (Some("as"), Some(name), None) if !name.contains('.') => {
+                    check_path(name, line_no)?;
+                    name.to_string()
+                }
+                _ => return Err(PromptError::parse(line_no, "`{{#each}}` 的写法为 `{{#each list}}` 或 `{{#each list as item}}`")),
+            };
+            let (body, _) = parse_nodes(iter, Some(("each", line_no)), in_role)?;
+            Ok(Node::Each {
+                list: split_path(list),
+                binding,
+                body,
+            })
+        }
+        name => match Role::parse(name) {
+            Some(_) if in_role => Err(PromptError::parse(line_no, "角色段落不能嵌套")),
+            Some(role) => {
+                if !args.is_empty() {
+                    return Err(PromptError::parse(line_no, format!("`{{{{#{}}}}}` 不接受参数", name)));
+                }
+                let (body, _) = parse_nodes(iter, Some((name, line_no)), true)?;
+                Ok(Node::Role { role, body })
+            }
+            None => Err(PromptError::parse(line_no, format!("未知的块标签 `{{{{#{}}}}}`", name))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> Vec<String> {
+        split_path(s)
+    }
+
+    fn parse_error(source: &str) -> (usize, String) {
+        match parse(source) {
+            Err(PromptError::Parse { line, message }) => (line, message),
+            other => panic!("应当解析失败: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn text_and_variables() {
+        assert_eq!(
+            parse("你好，{{ user.name }}！").unwrap(),
+            vec![
+                Node::Text(String::from("你好，")),
+                Node::Var(path("user.name")),
+                Node::Text(String::from("！")),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_braces() {
+        assert_eq!(
+            parse(r"\{{name}} {{name}}").unwrap(),
+            vec![Node::Text(String::from("{{name}} ")), Node::Var(path("name"))]
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        assert_eq!(
+            parse("{{#if a}}x{{else}}y{{/if}}").unwrap(),
+            vec![Node::If {
+                cond: path("a"),
+                then: vec![Node::Text(String::from("x"))],
+                otherwise: vec![Node::Text(String::from("y"))],
+            }]
+        );
+    }
+
+    #[test]
+    fn each_with_and_without_binding() {
+        assert_eq!(
+            parse("{{#each items}}{{this}}{{/each}}").unwrap(),
+            vec![Node::Each {
+                list: path("items"),
+                binding: String::from("this"),
+                body: vec![Node::Var(path("this"))],
+            }]
+        );
+        assert_eq!(
+            parse("{{#each items as item}}{{item.id}}{{/each}}").unwrap(),
+            vec![Node::Each {
+                list: path("items"),
+                binding: String::from("item"),
+                body: vec![Node::Var(path("item.id"))],
+            }]
+        );
+    }
+
+    #[test]
+    fn roles() {
+        assert_eq!(
+            parse("{{#system}}s{{/system}}{{#user}}u{{/user}}{{#assistant}}a{{/assistant}}").unwrap(),
+            vec![
+                Node::Role { role: Role::System, body: vec![Node::Text(String::from("s"))] },
+                Node::Role { role: Role::User, body: vec![Node::Text(String::from("u"))] },
+                Node::Role { role: Role::Assistant, body: vec![Node::Text(String::from("a"))] },
+            ]
+        );
+    }
+
+    #[test]
+    fn standalone_block_lines_are_removed() {
+        assert_eq!(
+            parse("{{#if a}}\n  x\n  {{/if}}\ny").unwrap(),
+            vec![
+                Node::If {
+                    cond: path("a"),
+                    then: vec![Node::Text(String::from("  x\n"))],
+                    otherwise: Vec::new(),
+                },
+                Node::Text(String::from("y")),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_tag() {
+        assert_eq!(parse_error("a\nb {{name").0, 2);
+    }
+
+    #[test]
+    fn empty_tag() {
+        assert_eq!(parse_error("{{ }}").0, 1);
+    }
+
+    #[test]
+    fn invalid_variable_name() {
+        parse_error("{{a b}}");
+        parse_error("{{a..b}}");
+        parse_error("{{#if a b}}{{/if}}");
+    }
+
+    #[test]
+    fn unclosed_block_reports_opening_line() {
+        assert_eq!(parse_error("x\n{{#if a}}\ny").0, 2);
+        parse_error("{{#each items}}");
+        parse_error("{{#user}}");
+    }
+
+    #[test]
+    fn mismatched_and_extra_closing_tags() {
+        parse_error("{{#if a}}{{/each}}");
+        parse_error("{{/if}}");
+    }
+
+    #[test]
+    fn misplaced_else() {
+        parse_error("{{else}}");
+        parse_error("{{#each a}}{{else}}{{/each}}");
+        parse_error("{{#if a}}x{{else}}y{{else}}z{{/if}}");
+    }
+
+    #[test]
+    fn invalid_each() {
+        parse_error("{{#each items in item}}{{/each}}");
+        parse_error("{{#each items as a.b}}{{/each}}");
+        parse_error("{{#each items as a b}}{{/each}}");
+    }
+
+    #[test]
+    fn invalid_roles() {
+        parse_error("{{#user}}{{#system}}x{{/system}}{{/user}}");
+        parse_error("{{#user name}}x{{/user}}");
+        parse_error("{{#unknown}}x{{/unknown}}");
+    }
+}
@@ -0,0 +1,419 @@
+use super::error::PromptError;
+use super::parser::{parse, Node, Role};
+use crate::chat::Message;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+/// 模板变量，值可以是字符串、数字、布尔值、列表或对象
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptVars(Map<String, Value>);
+
+impl PromptVars {
+    pub fn new() -> Self {
+        PromptVars(Map::new())
+    }
+    pub fn set(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.insert(name, value);
+        self
+    }
+    pub fn insert(&mut self, name: &str, value: impl Into<Value>) {
+        self.0.insert(name.to_string(), value.into());
+    }
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
+
+impl From<Map<String, Value>> for PromptVars {
+    fn from(map: Map<String, Value>) -> Self {
+        PromptVars(map)
+    }
+}
+
+impl From<HashMap<String, String>> for PromptVars {
+    fn from(map: HashMap<String, String>) -> Self {
+        PromptVars(map.into_iter().map(|(k, v)| (k, Value::String(v))).collect())
+    }
+}
+
+/// 提示词模板
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    nodes: Vec<Node>,
+    // 渲染时必须提供的变量
+    required: BTreeSet<String>,
+    // 模板中是否声明了角色
+    has_roles: bool,
+}
+
+impl PromptTemplate {
+    // 解析模板，语法错误在此时返回。
+    pub fn parse(source: &str) -> Result<Self, PromptError> {
+        let nodes = parse(source)?;
+        let has_roles = nodes.iter().any(contains_role);
+        if has_roles && nodes.iter().any(has_content_outside_role) {
+            return Err(PromptError::ContentOutsideRole);
+        }
+        let mut required = BTreeSet::new();
+        collect_required(&nodes, &mut Vec::new(), &mut Vec::new(), &mut required);
+        Ok(PromptTemplate {
+            nodes,
+            required,
+            has_roles,
+        })
+    }
+    // 从文件加载模板
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PromptError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(&source)
+    }
+    // 渲染时必须提供的变量。只用作 `{{#if}}` 条件的变量是可选的。
+    pub fn required_variables(&self) -> Vec<&str> {
+        self.required.iter().map(|s| s.as_str()).collect()
+    }
+    // 模板中是否声明了角色
+    pub fn has_roles(&self) -> bool {
+        self.has_roles
+    }
+    // 检查变量是否齐全，一次返回所有缺少的变量。
+    pub fn check(&self, vars: &PromptVars) -> Result<(), PromptError> {
+        let missing: Vec<String> = self
+            .required
+            .iter()
+            .filter(|name| !vars.contains(name))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(PromptError::MissingVariables(missing))
+        }
+    }
+    // 渲染为消息列表。没有声明角色的模板渲染为一条用户消息。
+    pub fn render(&self, vars: &PromptVars) -> Result<Vec<Message>, PromptError> {
+        self.check(vars)?;
+        let mut renderer = Renderer {
+            vars,
+            scopes: Vec::new(),
+            messages: Vec::new(),
+        };
+        let mut text = String::new();
+        renderer.render_nodes(&self.nodes, &mut text)?;
+        if self.has_roles {
+            Ok(renderer.messages)
+        } else {
+            Ok(vec![Message::user_message(text.trim())])
+        }
+    }
+    // 渲染为一段文本，保留首尾空白，用于单条消息的内容。模板中不能声明角色。
+    pub fn render_str(&self, vars: &PromptVars) -> Result<String, PromptError> {
+        if self.has_roles {
+            return Err(PromptError::RoleNotAllowed);
+        }
+        self.check(vars)?;
+        let mut renderer = Renderer {
+            vars,
+            scopes: Vec::new(),
+            messages: Vec::new(),
+        };
+        let mut text = String::new();
+        renderer.render_nodes(&self.nodes, &mut text)?;
+        Ok(text)
+    }
+    // 渲染为纯文本，角色声明会被忽略，各段落内容依次拼接。
+    pub fn render_text(&self, vars: &PromptVars) -> Result<String, PromptError> {
+        let messages = self.render(vars)?;
+        let texts: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        Ok(texts.join("\n\n"))
+    }
+}
+
+impl FromStr for PromptTemplate {
+    type Err = PromptError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn contains_role(node: &Node) -> bool {
+    match node {
+        Node::Role { .. } => true,
+        Node::If { then, otherwise, .. } => then.iter().chain(otherwise).any(contains_role),
+        Node::Each { body, .. } => body.iter().any(contains_role),
+        _ => false,
+    }
+}
+
+fn has_content_outside_role(node: &Node) -> bool {
+    match node {
+        Node::Text(text) => !text.trim().is_empty(),
+        Node::Var(_) => true,
+        Node::If { then, otherwise, .. } => then.iter().chain(otherwise).any(has_content_outside_role),
+        Node::Each { body, .. } => body.iter().any(has_content_outside_role),
+        Node::Role { .. } => false,
+    }
+}
+
+// bindings 为循环变量，guards 为外层 `{{#if}}` 已经判断过的变量
+fn collect_required(
+    nodes: &[Node],
+    bindings: &mut Vec<String>,
+    guards: &mut Vec<Vec<String>>,
+    required: &mut BTreeSet<String>,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var(path) => require(path, bindings, guards, required),
+            Node::If { cond, then, otherwise } => {
+                guards.push(cond.clone());
+                collect_required(then, bindings, guards, required);
+                guards.pop();
+                collect_required(otherwise, bindings, guards, required);
+            }
+            Node::Each { list, binding, body } => {
+                require(list, bindings, guards, required);
+                bindings.push(binding.clone());
+                collect_required(body, bindings, guards, required);
+                bindings.pop();
+            }
+            Node::Role { body, .. } => collect_required(body, bindings, guards, required),
+        }
+    }
+}
+
+fn require(path: &[String], bindings: &[String], guards: &[Vec<String>], required: &mut BTreeSet<String>) {
+    let bound = bindings.contains(&path[0]);
+    let guarded = guards.iter().any(|g| path.starts_with(g));
+    if !bound && !guarded {
+        required.insert(path[0].clone());
+    }
+}
+
+struct Renderer<'a> {
+    vars: &'a PromptVars,
+    // 循环变量，内层在后
+    scopes: Vec<(&'a str, &'a Value)>,
+    messages: Vec<Message>,
+}
+
+impl<'a> Renderer<'a> {
+    fn lookup(&self, path: &[String]) -> Option<&'a Value> {
+        let root = self
+            .scopes
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == path[0])
+            .map(|(_, value)| *value)
+            .or_else(|| self.vars.get(&path[0]))?;
+        path[1..].iter().try_fold(root, |value, key| value.get(key))
+    }
+
+    fn render_nodes(&mut self, nodes: &'a [Node], out: &mut String) -> Result<(), PromptError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var(path) => {
+                    let value = self
+                        .lookup(path)
+                        .ok_or_else(|| PromptError::MissingVariables(vec![path.join(".")]))?;
+                    match value {
+                        Value::Null => {}
+                        Value::String(s) => out.push_str(s),
+                        Value::Number(n) => out.push_str(&n.to_string()),
+                        Value::Bool(b) => out.push_str(&b.to_string()),
+                        _ => {
+                            return Err(PromptError::TypeMismatch {
+                                name: path.join("."),
+                                expected: "字符串、数字或布尔值",
+                            });
+                        }
+                    }
+                }
+                Node::If { cond, then, otherwise } => {
+                    if self.lookup(cond).is_some_and(truthy) {
+                        self.render_nodes(then, out)?;
+                    } else {
+                        self.render_nodes(otherwise, out)?;
+                    }
+                }
+                Node::Each { list, binding, body } => {
+                    let items = match self.lookup(list) {
+                        Some(Value::Array(items)) => items,
+                        Some(Value::Null) => continue,
+                        Some(_) => {
+                            return Err(PromptError::TypeMismatch {
+                                name: list.join("."),
+                                expected: "列表",
+                            });
+                        }
+                        None => return Err(PromptError::MissingVariables(vec![list.join(".")])),
+                    };
+                    for item in items {
+                        self.scopes.push((binding, item));
+                        let result = self.render_nodes(body, out);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+                Node::Role { role, body } => {
+                    let mut content = String::new();
+                    self.render_nodes(body, &mut content)?;
+                    let content = content.trim();
+                    self.messages.push(match role {
+                        Role::System => Message::system_message(content),
+                        Role::User => Message::user_message(content),
+                        Role::Assistant => Message::assistant_message(content),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 空字符串、false、0、空列表、空对象和 null 视为假
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, vars: PromptVars) -> Result<Vec<Message>, PromptError> {
+        PromptTemplate::parse(source)?.render(&vars)
+    }
+
+    fn text(source: &str, vars: PromptVars) -> String {
+        PromptTemplate::parse(source).unwrap().render_str(&vars).unwrap()
+    }
+
+    #[test]
+    fn variables_and_paths() {
+        let vars = PromptVars::new()
+            .set("name", "张三")
+            .set("age", 18)
+            .set("ok", true)
+            .set("none", Value::Null)
+            .set("user", json!({"city": "北京"}));
+        assert_eq!(
+            text("{{name}} {{age}} {{ok}} [{{none}}] {{user.city}}", vars),
+            "张三 18 true [] 北京"
+        );
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        assert_eq!(text(r"\{{name}}", PromptVars::new()), "{{name}}");
+    }
+
+    #[test]
+    fn conditionals() {
+        let source = "{{#if context}}有：{{context}}{{else}}无{{/if}}";
+        assert_eq!(text(source, PromptVars::new()), "无");
+        assert_eq!(text(source, PromptVars::new().set("context", "")), "无");
+        assert_eq!(text(source, PromptVars::new().set("context", json!([]))), "无");
+        assert_eq!(text(source, PromptVars::new().set("context", 0)), "无");
+        assert_eq!(text(source, PromptVars::new().set("context", "资料")), "有：资料");
+    }
+
+    #[test]
+    fn loops() {
+        let vars = PromptVars::new().set("items", json!([{"id": 1}, {"id": 2}]));
+        assert_eq!(text("{{#each items}}{{this.id}},{{/each}}", vars.clone()), "1,2,");
+        assert_eq!(text("{{#each items as item}}{{item.id}};{{/each}}", vars), "1;2;");
+        assert_eq!(text("{{#each items}}x{{/each}}", PromptVars::new().set("items", Value::Null)), "");
+    }
+
+    #[test]
+    fn roles_render_to_messages() {
+        let source = "{{#system}}\n你是{{language}}翻译\n{{/system}}\n{{#each examples}}\n{{#user}}{{this.source}}{{/user}}\n{{#assistant}}{{this.target}}{{/assistant}}\n{{/each}}\n{{#user}}{{text}}{{/user}}\n";
+        let vars = PromptVars::new()
+            .set("language", "英语")
+            .set("examples", json!([{"source": "你好", "target": "Hello"}]))
+            .set("text", "谢谢");
+        let messages = render(source, vars).unwrap();
+        let pairs: Vec<(&str, &str)> = messages.iter().map(|m| (m.role(), m.content())).collect();
+        assert_eq!(
+            pairs,
+            vec![("system", "你是英语翻译"), ("user", "你好"), ("assistant", "Hello"), ("user", "谢谢")]
+        );
+    }
+
+    #[test]
+    fn without_roles_renders_one_user_message() {
+        let messages = render("\n  {{text}}  \n", PromptVars::new().set("text", "hi")).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!((messages[0].role(), messages[0].content()), ("user", "hi"));
+    }
+
+    #[test]
+    fn required_variables_skip_guards_and_bindings() {
+        let template = PromptTemplate::parse(
+            "{{#if context}}{{context}}{{/if}}{{#each items as item}}{{item}}{{/each}}{{text}}",
+        )
+        .unwrap();
+        assert_eq!(template.required_variables(), vec!["items", "text"]);
+    }
+
+    #[test]
+    fn missing_variables_are_reported_together() {
+        match render("{{a}}{{b}}{{#if c}}{{c}}{{/if}}", PromptVars::new()) {
+            Err(PromptError::MissingVariables(names)) => assert_eq!(names, vec!["a", "b"]),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_nested_field() {
+        match render("{{user.name}}", PromptVars::new().set("user", json!({}))) {
+            Err(PromptError::MissingVariables(names)) => assert_eq!(names, vec!["user.name"]),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let err = render("{{user}}", PromptVars::new().set("user", json!({"a": 1}))).unwrap_err();
+        assert!(matches!(err, PromptError::TypeMismatch { ref name, .. } if name == "user"));
+        let err = render("{{#each items}}{{/each}}", PromptVars::new().set("items", "x")).unwrap_err();
+        assert!(matches!(err, PromptError::TypeMismatch { ref name, .. } if name == "items"));
+    }
+
+    #[test]
+    fn content_outside_role() {
+        let err = PromptTemplate::parse("x{{#user}}y{{/user}}").unwrap_err();
+        assert!(matches!(err, PromptError::ContentOutsideRole));
+    }
+
+    #[test]
+    fn roles_not_allowed_in_render_str() {
+        let template = PromptTemplate::parse("{{#user}}y{{/user}}").unwrap();
+        assert!(matches!(template.render_str(&PromptVars::new()), Err(PromptError::RoleNotAllowed)));
+    }
+
+    #[test]
+    fn parse_error_from_str() {
+        assert!(matches!("{{#if a}}".parse::<PromptTemplate>(), Err(PromptError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn missing_file() {
+        let err = PromptTemplate::from_file("/nonexistent/prompt.txt").unwrap_err();
+        assert!(matches!(err, PromptError::Io(_)));
+    }
+}
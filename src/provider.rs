@@ -0,0 +1,177 @@
+//! # 接口提供方
+//! DeepSeek 的接口与 OpenAI 兼容，[`Provider`] 描述一个 OpenAI 兼容服务的地址、鉴权方式、模型名称和支持的功能，
+//! 同一个客户端可以访问 vLLM、llama.cpp server 等本地服务：
+//!
+//! - 地址和默认模型在设置 provider 时写入客户端
+//! - 请求中的模型 id 按 [`Provider::model_alias`] 换成服务上的名称
+//! - 调用服务不支持的功能（例如 FIM、余额）时直接返回 [`DeepSeekError::Unsupported`]，不发送请求
+//! - 响应中没有的用量字段（例如 `prompt_cache_hit_tokens`）按缺省值处理，见 `Usage`
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::base_types::data::ModelName;
+//! use deepseek_rs::chat::*;
+//! use deepseek_rs::provider::Provider;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! let provider = Provider::vllm("http://localhost:8000/v1")
+//!     .default_model(ModelName::custom("Qwen/Qwen2.5-7B-Instruct"))
+//!     // 代码中使用 deepseek-chat 的请求会发给这个模型
+//!     .model_alias(ModelName::DeepseekChat, "Qwen/Qwen2.5-7B-Instruct");
+//! let client = DeepSeekClient::new("").provider(provider);
+//! let (_, request) = client.chat_builder().add_message(Message::user_message("你好")).build();
+//! println!("{:?}", client.chat(&request).await?.content());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekError::Unsupported`]: crate::DeepSeekError::Unsupported
+use crate::base_types::data::ModelName;
+use crate::base_types::endpoint::DEFAULT_BASE_URL;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// API Key 的发送方式
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AuthScheme {
+    // `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    // 把 key 原样放在指定的请求头中，例如 `api-key`
+    Header(String),
+    // 不发送 key，例如没有设置 --api-key 的本地服务
+    None,
+}
+
+/// provider 可能不支持的功能
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    // FIM 补全（/beta/completions）
+    Fim,
+    // 余额查询（/user/balance）
+    Balance,
+    // 模型列表（/models）
+    Models,
+    // response_format 为 json_object
+    JsonMode,
+    // 请求中的 tools
+    Tools,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Capability::Fim => write!(f, "FIM 补全"),
+            Capability::Balance => write!(f, "余额查询"),
+            Capability::Models => write!(f, "模型列表"),
+            Capability::JsonMode => write!(f, "JSON 模式"),
+            Capability::Tools => write!(f, "tool 调用"),
+        }
+    }
+}
+
+/// OpenAI 兼容的接口提供方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provider {
+    name: String,
+    base_url: String,
+    auth: AuthScheme,
+    default_model: ModelName,
+    // 本库的模型 id 到服务上的模型 id
+    aliases: HashMap<String, String>,
+    capabilities: BTreeSet<Capability>,
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Self::deepseek()
+    }
+}
+
+impl Provider {
+    // DeepSeek 官方接口，支持所有功能
+    pub fn deepseek() -> Self {
+        Provider {
+            name: String::from("deepseek"),
+            base_url: String::from(DEFAULT_BASE_URL),
+            auth: AuthScheme::Bearer,
+            default_model: ModelName::DeepseekChat,
+            aliases: HashMap::new(),
+            capabilities: BTreeSet::from([
+                Capability::Fim,
+                Capability::Balance,
+                Capability::Models,
+                Capability::JsonMode,
+                Capability::Tools,
+            ]),
+        }
+    }
+    // 一般的 OpenAI 兼容服务，base_url 包括 `/v1` 等前缀；支持模型列表、JSON 模式和 tool 调用
+    pub fn openai_compatible(name: &str, base_url: &str) -> Self {
+        Provider {
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth: AuthScheme::Bearer,
+            default_model: ModelName::DeepseekChat,
+            aliases: HashMap::new(),
+            capabilities: BTreeSet::from([Capability::Models, Capability::JsonMode, Capability::Tools]),
+        }
+    }
+    // vLLM 的 OpenAI 兼容服务，例如 `http://localhost:8000/v1`
+    pub fn vllm(base_url: &str) -> Self {
+        Self::openai_compatible("vllm", base_url)
+    }
+    // llama.cpp server，例如 `http://localhost:8080/v1`
+    pub fn llama_cpp(base_url: &str) -> Self {
+        Self::openai_compatible("llama.cpp", base_url)
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    pub fn auth(mut self, auth: AuthScheme) -> Self {
+        self.auth = auth;
+        self
+    }
+    // 默认模型，设置 provider 时写入客户端
+    pub fn default_model(mut self, model: ModelName) -> Self {
+        self.default_model = model;
+        self
+    }
+    // 发送请求时把 model 换成服务上的 id
+    pub fn model_alias(mut self, model: ModelName, id: &str) -> Self {
+        self.aliases.insert(model.to_string(), id.to_string());
+        self
+    }
+    // 声明支持某项功能
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+    // 声明不支持某项功能
+    pub fn without(mut self, capability: Capability) -> Self {
+        self.capabilities.remove(&capability);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_base_url(&self) -> &str {
+        &self.base_url
+    }
+    pub fn get_auth(&self) -> &AuthScheme {
+        &self.auth
+    }
+    pub fn get_default_model(&self) -> &ModelName {
+        &self.default_model
+    }
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+    // 服务上的模型 id，没有设置别名时原样返回
+    pub fn model_id<'a>(&'a self, model: &'a str) -> &'a str {
+        self.aliases.get(model).map(String::as_str).unwrap_or(model)
+    }
+}
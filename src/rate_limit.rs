@@ -0,0 +1,351 @@
+//! # 限流
+//! 客户端侧的限流器，同时限制每分钟请求数（RPM）和每分钟 token 数（TPM）。
+//! 发送前按提示词长度和 `max_tokens` 估算 token 数并预扣，收到响应中的 `Usage` 后按实际用量多退少补。
+//! 等待的调用按先来先到的顺序排队，[`RateLimiter::acquire`] 会等到额度足够再返回，而不是直接失败。
+//! 限额为 0 表示不限制该项。
+//! 开启自适应后，收到 429 会降低限额（并遵守 `Retry-After`），之后随成功的请求逐步恢复。
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::rate_limit::{RateLimitLayer, RateLimiter};
+//!
+//! let limiter = Arc::new(
+//!     RateLimiter::new()
+//!         .requests_per_minute(60)
+//!         .tokens_per_minute(100_000)
+//!         .adaptive(true),
+//! );
+//! // 多个客户端可以共享同一个限流器
+//! let client = DeepSeekClient::new("sk-...").layer(RateLimitLayer::new(limiter.clone()));
+//! ```
+use crate::chat::{ChatCompletionChunk, ChatResponse, Usage};
+use crate::completions::FimResponse;
+use crate::http::{HttpRequest, HttpResponse};
+use crate::middleware::{Middleware, Next, RequestContext};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 没有设置 `max_tokens` 时预估的输出 token 数
+pub const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+// 自适应调整时限额的最低比例
+const MIN_SCALE: f64 = 0.1;
+// 每次 429 后限额乘以的比例
+const BACKOFF: f64 = 0.7;
+// 每次成功后限额恢复的比例
+const RECOVERY: f64 = 0.02;
+// 超过这个时间还没有对账的预扣记录会被清理
+const PENDING_TTL: Duration = Duration::from_secs(600);
+
+/// 估算文本的 token 数：英文字符约 0.3 个 token，中文等其他字符约 0.6 个 token
+pub fn estimate_tokens(text: &str) -> u64 {
+    let (ascii, other) = text
+        .chars()
+        .fold((0u64, 0u64), |(a, o), c| if c.is_ascii() { (a + 1, o) } else { (a, o + 1) });
+    ((ascii * 3).div_ceil(10)) + ((other * 6).div_ceil(10))
+}
+
+/// 按请求体估算一次请求消耗的 token 数，包括提示词和 `max_tokens`
+pub fn estimate_request_tokens(body: &str, default_max_tokens: u64) -> u64 {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return 0;
+    };
+    let mut tokens = 0;
+    if let Some(messages) = value.get("messages").and_then(|m| m.as_array()) {
+        for message in messages {
+            if let Some(content) = message.get("content").and_then(|c| c.as_str()) {
+                tokens += estimate_tokens(content);
+            }
+        }
+    }
+    if let Some(prompt) = value.get("prompt").and_then(|p| p.as_str()) {
+        tokens += estimate_tokens(prompt);
+    }
+    tokens + value.get("max_tokens").and_then(|m| m.as_u64()).unwrap_or(default_max_tokens)
+}
+
+// 令牌桶，额度按每分钟的限额连续恢复
+#[derive(Debug)]
+struct Bucket {
+    per_minute: Option<u64>,
+    available: f64,
+}
+
+impl Bucket {
+    fn new(per_minute: Option<u64>) -> Self {
+        Bucket {
+            per_minute,
+            available: per_minute.unwrap_or_default() as f64,
+        }
+    }
+    fn capacity(&self, scale: f64) -> f64 {
+        self.per_minute.unwrap_or_default() as f64 * scale
+    }
+    fn refill(&mut self, elapsed: Duration, scale: f64) {
+        let capacity = self.capacity(scale);
+        self.available = (self.available + capacity * elapsed.as_secs_f64() / 60.0).min(capacity);
+    }
+    // 还需要等待多久才有足够的额度，超过容量的请求等到桶满即可
+    fn wait(&self, amount: f64, scale: f64) -> Duration {
+        if self.per_minute.is_none() {
+            return Duration::ZERO;
+        }
+        let capacity = self.capacity(scale);
+        let missing = amount.min(capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing * 60.0 / capacity)
+        }
+    }
+    fn take(&mut self, amount: f64) {
+        if self.per_minute.is_some() {
+            self.available -= amount;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    requests: Bucket,
+    tokens: Bucket,
+    // 当前限额相对配置的比例，只在自适应时变化
+    scale: f64,
+    // 收到 429 后暂停到这个时间
+    paused_until: Option<Instant>,
+    updated: Instant,
+}
+
+impl State {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.updated;
+        self.requests.refill(elapsed, self.scale);
+        self.tokens.refill(elapsed, self.scale);
+        self.updated = now;
+    }
+}
+
+/// 限流器，通过 `Arc` 在多个任务和客户端之间共享
+#[derive(Debug)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+    // 等待额度的调用在这里排队，tokio 的 Mutex 按先来先到的顺序唤醒
+    queue: tokio::sync::Mutex<()>,
+    adaptive: bool,
+    default_max_tokens: u64,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    // 不限流，通过下面的方法设置限额
+    pub fn new() -> Self {
+        RateLimiter {
+            state: Mutex::new(State {
+                requests: Bucket::new(None),
+                tokens: Bucket::new(None),
+                scale: 1.0,
+                paused_until: None,
+                updated: Instant::now(),
+            }),
+            queue: tokio::sync::Mutex::new(()),
+            adaptive: false,
+            default_max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+    // 每分钟请求数，为 0 时不限制请求数
+    pub fn requests_per_minute(self, limit: u64) -> Self {
+        self.state.lock().unwrap().requests = Bucket::new(Some(limit).filter(|limit| *limit > 0));
+        self
+    }
+    // 每分钟 token 数，为 0 时不限制 token 数
+    pub fn tokens_per_minute(self, limit: u64) -> Self {
+        self.state.lock().unwrap().tokens = Bucket::new(Some(limit).filter(|limit| *limit > 0));
+        self
+    }
+    // 是否根据 429 响应自动调整限额，默认关闭
+    pub fn adaptive(mut self, adaptive: bool) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+    // 请求没有设置 max_tokens 时预估的输出 token 数
+    pub fn default_max_tokens(mut self, tokens: u64) -> Self {
+        self.default_max_tokens = tokens;
+        self
+    }
+    // 当前限额相对配置的比例，未开启自适应时始终为 1
+    pub fn scale(&self) -> f64 {
+        self.state.lock().unwrap().scale
+    }
+
+    /// 等待一次请求和 `tokens` 个 token 的额度，返回的 [`Permit`] 可以在拿到实际用量后对账
+    pub async fn acquire(&self, tokens: u64) -> Permit<'_> {
+        let _turn = self.queue.lock().await;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                state.refill();
+                let now = Instant::now();
+                let paused = state.paused_until.filter(|until| *until > now).map(|until| until - now);
+                let wait = paused
+                    .unwrap_or_default()
+                    .max(state.requests.wait(1.0, state.scale))
+                    .max(state.tokens.wait(tokens as f64, state.scale));
+                if wait.is_zero() {
+                    state.requests.take(1.0);
+                    state.tokens.take(tokens as f64);
+                }
+                wait
+            };
+            if wait.is_zero() {
+                return Permit {
+                    limiter: self,
+                    estimated: tokens,
+                };
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // 按实际用量修正预扣的 token 数，实际用量大于预估时额度可以为负
+    pub fn reconcile(&self, estimated: u64, actual: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.refill();
+        state.tokens.take(actual as f64 - estimated as f64);
+    }
+
+    // 收到 429：暂停到 retry_after 之后；开启自适应时降低限额
+    pub fn on_rate_limited(&self, retry_after: Option<Duration>) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.refill();
+        if let Some(retry_after) = retry_after {
+            let until = Instant::now() + retry_after;
+            state.paused_until = Some(state.paused_until.map_or(until, |u| u.max(until)));
+        }
+        if self.adaptive {
+            state.scale = (state.scale * BACKOFF).max(MIN_SCALE);
+            let scale = state.scale;
+            for bucket in [&mut state.requests, &mut state.tokens] {
+                bucket.available = bucket.available.min(bucket.capacity(scale));
+            }
+        }
+    }
+
+    // 请求成功，开启自适应时逐步恢复限额
+    pub fn on_success(&self) {
+        if self.adaptive {
+            let mut state = self.state.lock().unwrap();
+            state.refill();
+            state.scale = (state.scale + RECOVERY).min(1.0);
+        }
+    }
+}
+
+/// 已经获得的额度
+#[derive(Debug)]
+pub struct Permit<'a> {
+    limiter: &'a RateLimiter,
+    estimated: u64,
+}
+
+impl Permit<'_> {
+    // 预扣的 token 数
+    pub fn estimated(&self) -> u64 {
+        self.estimated
+    }
+    // 按实际用量对账
+    pub fn reconcile(self, usage: &Usage) {
+        self.limiter.reconcile(self.estimated, usage.total_tokens() as u64);
+    }
+    // 请求没有消耗 token（例如被拒绝），退回预扣的额度
+    pub fn refund(self) {
+        self.limiter.reconcile(self.estimated, 0);
+    }
+}
+
+/// 限流中间件，发送前等待额度，收到响应后按 `Usage` 对账
+#[derive(Debug)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+    // 等待对账的请求：request_id -> (发送时间, 预扣的 token 数)
+    pending: Mutex<HashMap<String, (Instant, u64)>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        RateLimitLayer {
+            limiter,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    fn reconcile(&self, ctx: &RequestContext, usage: &Usage) {
+        let pending = self.pending.lock().unwrap().remove(ctx.request_id());
+        if let Some((_, estimated)) = pending {
+            self.limiter.reconcile(estimated, usage.total_tokens() as u64);
+        }
+    }
+}
+
+impl Middleware for RateLimitLayer {
+    fn handle<'a>(&'a self, request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let ctx = next.context();
+            let tokens = match ctx.model() {
+                Some(_) => estimate_request_tokens(request.body().unwrap_or_default(), self.limiter.default_max_tokens),
+                None => 0,
+            };
+            let permit = self.limiter.acquire(tokens).await;
+            let result = next.run(request).await;
+            match &result {
+                Ok(response) if response.is_success() => {
+                    self.limiter.on_success();
+                    if tokens > 0 {
+                        let mut pending = self.pending.lock().unwrap();
+                        pending.retain(|_, (sent, _)| sent.elapsed() < PENDING_TTL);
+                        pending.insert(ctx.request_id().to_string(), (Instant::now(), permit.estimated()));
+                    }
+                }
+                Ok(response) => {
+                    if response.status() == 429 {
+                        self.limiter.on_rate_limited(response.retry_after());
+                    }
+                    permit.refund();
+                }
+                Err(_) => permit.refund(),
+            }
+            result
+        })
+    }
+
+    fn on_chat_response(&self, response: &mut ChatResponse, ctx: &RequestContext) {
+        if let Some(usage) = response.usage() {
+            self.reconcile(ctx, usage);
+        }
+    }
+
+    fn on_chunk(&self, chunk: &mut ChatCompletionChunk, ctx: &RequestContext) {
+        if let Some(usage) = chunk.usage() {
+            self.reconcile(ctx, usage);
+        }
+    }
+
+    fn on_fim_response(&self, response: &mut FimResponse, ctx: &RequestContext) {
+        if let Some(usage) = response.usage() {
+            self.reconcile(ctx, usage);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! # 重试
+//! 网络错误、超时、429 和 5xx 响应按指数退避重试，429 响应中的 `Retry-After` 优先。
+//! 流式请求只在收到响应头之前重试。
+use crate::error::DeepSeekError;
+use std::time::Duration;
+
+/// 重试策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // 最多重试的次数，不包括第一次请求
+    max_retries: u32,
+    // 第一次重试前的等待时间，之后每次翻倍
+    base_delay: Duration,
+    // 单次等待的上限
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // 默认重试 3 次，从 500ms 开始翻倍，最多等待 30s
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // 不重试
+    pub fn none() -> Self {
+        Self::default().max_retries(0)
+    }
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+    pub fn get_max_retries(&self) -> u32 {
+        self.max_retries
+    }
+    // 错误是否值得重试：网络错误、超时、429 和 5xx
+    pub fn is_retryable(err: &DeepSeekError) -> bool {
+        match err {
+            DeepSeekError::Transport(_) | DeepSeekError::Timeout { .. } => true,
+            DeepSeekError::Api { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+    // 第 attempt 次重试（从 1 开始）前的等待时间
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        retry_after.unwrap_or(backoff).min(self.max_delay)
+    }
+}
@@ -0,0 +1,176 @@
+//! # SSE 解析
+//! 把字节流解析为 server-sent events，chunk 可以在任意位置（包括 UTF-8 字符中间）断开。
+//! 解码器不依赖任何异步运行时，原生和 wasm 的客户端都用它解析流式响应。
+use crate::error::DeepSeekError;
+use crate::lenient::{self, Drift};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// 一个 SSE 事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    event: Option<String>,
+    data: String,
+}
+
+impl SseEvent {
+    pub fn event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+    // 流结束标记 `data: [DONE]`
+    pub fn is_done(&self) -> bool {
+        self.data == "[DONE]"
+    }
+}
+
+/// SSE 解码器
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    // 还没有遇到换行的字节
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // 输入一段字节，返回其中已经完整的事件
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+    // 输入结束，返回最后一个没有以空行结尾的事件
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            if let Some(event) = self.line(line.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // 以冒号开头的是注释，例如 `: keep-alive`
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+type Parse<T> = fn(&str) -> Result<(T, Vec<Drift>), serde_json::Error>;
+
+/// 把每个事件的 data 解析为 JSON，遇到 `data: [DONE]` 后忽略之后的输入
+#[derive(Debug)]
+pub struct JsonEventDecoder<T> {
+    decoder: SseDecoder,
+    done: bool,
+    parse: Parse<T>,
+    // 严格模式下发现的、还没有被取走的不一致
+    drift: Vec<Drift>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Default for JsonEventDecoder<T> {
+    fn default() -> Self {
+        JsonEventDecoder {
+            decoder: SseDecoder::new(),
+            done: false,
+            parse: |data| Ok((serde_json::from_str(data)?, Vec::new())),
+            drift: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Serialize> JsonEventDecoder<T> {
+    // 严格模式：用 [`lenient::check`] 解析每个事件，不一致的地方通过 take_drift 取出
+    pub fn strict() -> Self {
+        JsonEventDecoder {
+            parse: lenient::check::<T>,
+            ..Self::default()
+        }
+    }
+}
+
+impl<T: DeserializeOwned> JsonEventDecoder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // 输入一段字节，返回其中已经完整的事件；解析失败的事件返回错误，调用方应在第一个错误处停止
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Result<T, DeepSeekError>> {
+        if self.done {
+            return Vec::new();
+        }
+        let mut items = Vec::new();
+        for event in self.decoder.feed(chunk) {
+            if event.is_done() {
+                self.done = true;
+                break;
+            }
+            items.push(match (self.parse)(event.data()) {
+                Ok((item, drift)) => {
+                    self.drift.extend(drift);
+                    Ok(item)
+                }
+                Err(err) => Err(err.into()),
+            });
+        }
+        items
+    }
+    // 取出严格模式下发现的不一致，非严格模式下总是为空
+    pub fn take_drift(&mut self) -> Vec<Drift> {
+        std::mem::take(&mut self.drift)
+    }
+    // 是否已经收到 [DONE]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    // 输入结束，没有收到 [DONE] 时返回错误
+    pub fn finish(&mut self) -> Result<(), DeepSeekError> {
+        if self.done || self.decoder.finish().is_some_and(|e| e.is_done()) {
+            self.done = true;
+            return Ok(());
+        }
+        Err(DeepSeekError::Stream(String::from("连接在 [DONE] 之前断开")))
+    }
+}
@@ -0,0 +1,114 @@
+//! # 默认响应
+//! 与 DeepSeek 接口格式一致的示例数据，脚本中没有安排响应时使用。
+use serde_json::{json, Value};
+
+pub const CREATED: i64 = 1_700_000_000;
+
+fn usage(prompt_tokens: usize, completion_tokens: usize) -> Value {
+    json!({
+        "completion_tokens": completion_tokens,
+        "prompt_tokens": prompt_tokens,
+        "prompt_cache_hit_tokens": 0,
+        "prompt_cache_miss_tokens": prompt_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+        "prompt_tokens_details": { "cached_tokens": 0 }
+    })
+}
+
+/// /chat/completions 的非流式响应
+pub fn chat_response(content: &str) -> Value {
+    json!({
+        "id": "mock-chat-0",
+        "object": "chat.completion",
+        "created": CREATED,
+        "model": "deepseek-chat",
+        "system_fingerprint": "fp_mock",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "logprobs": null,
+            "finish_reason": "stop"
+        }],
+        "usage": usage(10, content.chars().count())
+    })
+}
+
+/// /chat/completions 的流式响应，每个字符一个 chunk，最后一个 chunk 携带 finish_reason 和用量。
+pub fn chat_chunks(content: &str) -> Vec<Value> {
+    let chunk = |delta: Value, finish_reason: Value, usage: Value| {
+        json!({
+            "id": "mock-chat-0",
+            "object": "chat.completion.chunk",
+            "created": CREATED,
+            "model": "deepseek-chat",
+            "system_fingerprint": "fp_mock",
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "logprobs": null,
+                "finish_reason": finish_reason
+            }],
+            "usage": usage
+        })
+    };
+    let mut chunks = vec![chunk(json!({ "role": "assistant", "content": "" }), Value::Null, Value::Null)];
+    for c in content.chars() {
+        chunks.push(chunk(json!({ "content": c.to_string() }), Value::Null, Value::Null));
+    }
+    chunks.push(chunk(json!({ "content": "" }), json!("stop"), usage(10, content.chars().count())));
+    chunks
+}
+
+/// /beta/completions 的响应
+pub fn fim_response(text: &str) -> Value {
+    json!({
+        "id": "mock-fim-0",
+        "object": "text_completion",
+        "created": CREATED,
+        "model": "deepseek-chat",
+        "system_fingerprint": "fp_mock",
+        "choices": [{
+            "index": 0,
+            "text": text,
+            "logprobs": null,
+            "finish_reason": "stop"
+        }],
+        "usage": usage(10, text.chars().count())
+    })
+}
+
+/// /models 的响应
+pub fn models() -> Value {
+    json!({
+        "object": "list",
+        "data": [
+            { "id": "deepseek-chat", "object": "model", "owned_by": "deepseek" },
+            { "id": "deepseek-reasoner", "object": "model", "owned_by": "deepseek" }
+        ]
+    })
+}
+
+/// /user/balance 的响应
+pub fn balance() -> Value {
+    json!({
+        "is_available": true,
+        "balance_infos": [{
+            "currency": "CNY",
+            "total_balance": "110.00",
+            "granted_balance": "10.00",
+            "topped_up_balance": "100.00"
+        }]
+    })
+}
+
+/// 错误响应
+pub fn error(message: &str, error_type: &str) -> Value {
+    json!({
+        "error": {
+            "message": message,
+            "type": error_type,
+            "param": null,
+            "code": null
+        }
+    })
+}
@@ -0,0 +1,21 @@
+//! # 离线测试工具
+//! 需要开启 `testing` 特性。提供一个进程内的模拟 DeepSeek 服务器，
+//! 可以在没有网络和 API Key 的情况下测试请求与响应的处理。
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::testing::{MockResponse, MockServer, Route};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let server = MockServer::start().await?;
+//! server.push(Route::ChatCompletions, MockResponse::rate_limited());
+//! server.push(Route::ChatCompletions, MockResponse::chat("你好"));
+//! let client = DeepSeekClient::new("sk-test").base_url(&server.url());
+//! // 用 client 发送请求后，用 server.requests() 检查请求内容
+//! # Ok(())
+//! # }
+//! ```
+pub mod fixtures;
+pub mod server;
+
+pub use server::*;
@@ -0,0 +1,439 @@
+//! # 模拟 DeepSeek 服务器
+//! 在本地端口上提供 `/chat/completions`（普通与 SSE）、`/beta/completions`、`/models`
+//! 和 `/user/balance`，可以按顺序安排响应、设置延迟、注入错误，并记录收到的请求和客户端提前断开的连接。
+use super::fixtures;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// 模拟服务器支持的接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    ChatCompletions,
+    FimCompletions,
+    Models,
+    Balance,
+}
+
+impl Route {
+    pub fn path(&self) -> &'static str {
+        match self {
+            Route::ChatCompletions => "/chat/completions",
+            Route::FimCompletions => "/beta/completions",
+            Route::Models => "/models",
+            Route::Balance => "/user/balance",
+        }
+    }
+    fn from_path(path: &str) -> Option<Route> {
+        let path = path.split('?').next().unwrap_or(path);
+        [Route::ChatCompletions, Route::FimCompletions, Route::Models, Route::Balance]
+            .into_iter()
+            .find(|route| route.path() == path)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum MockBody {
+    Full(String),
+    // SSE 事件的 data 内容，不含结尾的 [DONE]
+    Stream {
+        events: Vec<String>,
+        interval: Duration,
+        truncated: bool,
+    },
+}
+
+/// 安排给模拟服务器的响应
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: MockBody,
+    latency: Option<Duration>,
+}
+
+impl MockResponse {
+    // 状态码 200 的 JSON 响应
+    pub fn json(value: Value) -> Self {
+        MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: MockBody::Full(value.to_string()),
+            latency: None,
+        }
+    }
+    // SSE 流式响应，每个值为一个 `data:` 事件，结尾自动追加 `data: [DONE]`
+    pub fn sse(events: Vec<Value>) -> Self {
+        MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: MockBody::Stream {
+                events: events.iter().map(|e| e.to_string()).collect(),
+                interval: Duration::ZERO,
+                truncated: false,
+            },
+            latency: None,
+        }
+    }
+    pub fn chat(content: &str) -> Self {
+        Self::json(fixtures::chat_response(content))
+    }
+    pub fn chat_stream(content: &str) -> Self {
+        Self::sse(fixtures::chat_chunks(content))
+    }
+    pub fn fim(text: &str) -> Self {
+        Self::json(fixtures::fim_response(text))
+    }
+    pub fn models() -> Self {
+        Self::json(fixtures::models())
+    }
+    pub fn balance() -> Self {
+        Self::json(fixtures::balance())
+    }
+    // 与 DeepSeek 格式一致的错误响应
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(fixtures::error(message, "invalid_request_error")).status(status)
+    }
+    // 429 请求速率超限
+    pub fn rate_limited() -> Self {
+        Self::error(429, "Rate limit reached").header("Retry-After", "1")
+    }
+    // 500 服务器内部错误
+    pub fn server_error() -> Self {
+        Self::error(500, "Internal server error")
+    }
+    // 状态码 200，但响应体不是合法的 JSON
+    pub fn malformed_json() -> Self {
+        MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: MockBody::Full(String::from("{\"id\": \"mock-broken\", \"choices\": [")),
+            latency: None,
+        }
+    }
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+    // 开始响应前的延迟，覆盖服务器的全局延迟
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+    // 流式响应中每个事件之间的间隔
+    pub fn chunk_interval(mut self, interval: Duration) -> Self {
+        if let MockBody::Stream { interval: i, .. } = &mut self.body {
+            *i = interval;
+        }
+        self
+    }
+    // 流式响应只发送一半事件，然后在 chunk 中途断开连接
+    pub fn truncated(mut self) -> Self {
+        if let MockBody::Stream { truncated, .. } = &mut self.body {
+            *truncated = true;
+        }
+        self
+    }
+}
+
+/// 服务器收到的请求
+#[derive(Debug, Clone)]
+pub struct CapturedRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl CapturedRequest {
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn route(&self) -> Option<Route> {
+        Route::from_path(&self.path)
+    }
+    // 按名称查找请求头，不区分大小写
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+    // 把请求体解析为 JSON
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_str(&self.body).ok()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    scripts: HashMap<Route, VecDeque<MockResponse>>,
+    requests: Vec<CapturedRequest>,
+    latency: Duration,
+    disconnects: usize,
+}
+
+/// 模拟服务器，drop 时停止
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    // 在 127.0.0.1 的随机端口上启动
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let accept_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let state = accept_state.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(socket, state).await;
+                });
+            }
+        });
+        Ok(MockServer { addr, state, handle })
+    }
+    // 服务器地址，例如 `http://127.0.0.1:12345`，可作为 base url 使用
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+    // 某个接口的完整地址
+    pub fn url_for(&self, route: Route) -> String {
+        format!("{}{}", self.url(), route.path())
+    }
+    // 为接口安排下一个响应，按先进先出的顺序使用；没有安排时返回默认响应
+    pub fn push(&self, route: Route, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        state.scripts.entry(route).or_default().push_back(response);
+    }
+    // 所有响应开始前的延迟
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+    // 收到的全部请求
+    pub fn requests(&self) -> Vec<CapturedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+    // 某个接口收到的请求
+    pub fn requests_to(&self, route: Route) -> Vec<CapturedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.route() == Some(route))
+            .collect()
+    }
+    pub fn clear_requests(&self) {
+        self.state.lock().unwrap().requests.clear();
+    }
+    // 响应发送完之前客户端就断开的连接数，例如请求被取消
+    pub fn disconnects(&self) -> usize {
+        self.state.lock().unwrap().disconnects
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(socket: TcpStream, state: Arc<Mutex<State>>) -> std::io::Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    let request = CapturedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    };
+    let route = request.route();
+    let (response, latency) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let scripted = route.and_then(|r| state.scripts.get_mut(&r).and_then(|q| q.pop_front()));
+        (scripted, state.latency)
+    };
+    let response = match (response, route) {
+        (Some(response), _) => response,
+        (None, Some(route)) => default_response(route, &request),
+        (None, None) => MockResponse::error(404, "Not found"),
+    };
+
+    let latency = response.latency.unwrap_or(latency);
+    let respond = async {
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        write_response(&mut writer, response).await
+    };
+    // 客户端不会再发送数据，读到 EOF 说明连接已经被关闭
+    let closed = async {
+        let mut buf = [0; 64];
+        while let Ok(1..) = reader.read(&mut buf).await {}
+    };
+    let disconnected = tokio::select! {
+        result = respond => result.is_err(),
+        _ = closed => true,
+    };
+    if disconnected {
+        state.lock().unwrap().disconnects += 1;
+    }
+    Ok(())
+}
+
+fn default_response(route: Route, request: &CapturedRequest) -> MockResponse {
+    match route {
+        Route::ChatCompletions => {
+            let stream = request
+                .json()
+                .and_then(|v| v.get("stream").and_then(Value::as_bool))
+                .unwrap_or(false);
+            if stream {
+                MockResponse::chat_stream("你好！")
+            } else {
+                MockResponse::chat("你好！")
+            }
+        }
+        Route::FimCompletions => MockResponse::fim("return a + b"),
+        Route::Models => MockResponse::models(),
+        Route::Balance => MockResponse::balance(),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        404 => "Not Found",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+async fn write_response<W: AsyncWriteExt + Unpin>(writer: &mut W, response: MockResponse) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\nConnection: close\r\n", response.status, reason(response.status));
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    match response.body {
+        MockBody::Full(body) => {
+            head.push_str(&format!(
+                "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            ));
+            writer.write_all(head.as_bytes()).await?;
+            writer.write_all(body.as_bytes()).await?;
+        }
+        MockBody::Stream { events, interval, truncated } => {
+            head.push_str("Content-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n");
+            writer.write_all(head.as_bytes()).await?;
+            writer.flush().await?;
+            let count = if truncated { events.len() / 2 } else { events.len() };
+            for (i, event) in events.iter().take(count).enumerate() {
+                if i > 0 && !interval.is_zero() {
+                    tokio::time::sleep(interval).await;
+                }
+                write_chunk(writer, &format!("data: {}\n\n", event)).await?;
+            }
+            if truncated {
+                // 声明的 chunk 长度大于实际发送的数据，然后断开连接
+                let partial = events.get(count).map(|e| e.as_str()).unwrap_or("{\"id\"");
+                let data = format!("data: {}", first_half(partial));
+                writer
+                    .write_all(format!("{:x}\r\n{}", data.len() + 16, data).as_bytes())
+                    .await?;
+                writer.flush().await?;
+                return writer.shutdown().await;
+            }
+            write_chunk(writer, "data: [DONE]\n\n").await?;
+            writer.write_all(b"0\r\n\r\n").await?;
+        }
+    }
+    writer.flush().await?;
+    writer.shutdown().await
+}
+
+// 前一半内容，在字符边界处截断，事件中可能有多字节字符
+fn first_half(text: &str) -> &str {
+    let mut cut = text.len() / 2;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    &text[..cut]
+}
+
+async fn write_chunk<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &str) -> std::io::Result<()> {
+    writer
+        .write_all(format!("{:x}\r\n{}\r\n", data.len(), data).as_bytes())
+        .await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_half_keeps_char_boundaries() {
+        assert_eq!(first_half("abcd"), "ab");
+        assert_eq!(first_half("你好"), "你");
+        // 7 个字节的一半落在“你”的中间
+        assert_eq!(first_half("a你好"), "a");
+        assert_eq!(first_half(""), "");
+    }
+}
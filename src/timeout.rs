@@ -0,0 +1,194 @@
+//! # 超时
+//! [`Timeouts`] 可以在客户端上设置默认值（[`DeepSeekClient::timeouts`]），也可以在单个请求上覆盖
+//! （`ChatRequestBuilder::timeouts`、`FimRequestBuilder::timeouts`、`HttpRequest::timeouts`）。
+//! 未设置的项不限时。超时返回 [`DeepSeekError::Timeout`]，`kind` 区分是哪一项超时。
+//!
+//! - 连接超时：建立 TCP/TLS 连接的时间，由传输层处理
+//! - 首字节超时：从发出请求到收到响应头的时间
+//! - 空闲超时：响应体相邻两个数据块之间的时间，流式响应中服务器发送的 keep-alive 注释也算数据块
+//! - 总超时：从发出请求到读完响应体（包括流式响应）的时间，每次重试重新计时
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::base_types::data::ModelName;
+//! use deepseek_rs::chat::*;
+//! use deepseek_rs::timeout::Timeouts;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! // chat 请求默认很快失败
+//! let client = DeepSeekClient::new("sk-...").timeouts(
+//!     Timeouts::new()
+//!         .connect(Duration::from_secs(5))
+//!         .first_byte(Duration::from_secs(10))
+//!         .total(Duration::from_secs(60)),
+//! );
+//! // reasoner 可能要思考几分钟
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .model(ModelName::DeepseekReasoner)
+//!     .add_message(Message::user_message("证明素数有无穷多个"))
+//!     .timeouts(Timeouts::new().total(Duration::from_secs(600)).idle(Duration::from_secs(60)))
+//!     .build();
+//! let mut stream = client.chat_stream(&request).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`DeepSeekClient::timeouts`]: crate::DeepSeekClient::timeouts
+//! [`DeepSeekError::Timeout`]: crate::DeepSeekError::Timeout
+#[cfg(feature = "client")]
+use crate::http::ByteStream;
+#[cfg(feature = "client")]
+use futures::StreamExt;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+#[cfg(feature = "client")]
+use tokio::time::Instant;
+
+/// 各项超时，None 表示不限时
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    connect: Option<Duration>,
+    total: Option<Duration>,
+    first_byte: Option<Duration>,
+    idle: Option<Duration>,
+}
+
+impl Timeouts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn connect(mut self, timeout: Duration) -> Self {
+        self.connect = Some(timeout);
+        self
+    }
+    pub fn total(mut self, timeout: Duration) -> Self {
+        self.total = Some(timeout);
+        self
+    }
+    pub fn first_byte(mut self, timeout: Duration) -> Self {
+        self.first_byte = Some(timeout);
+        self
+    }
+    pub fn idle(mut self, timeout: Duration) -> Self {
+        self.idle = Some(timeout);
+        self
+    }
+    pub fn get_connect(&self) -> Option<Duration> {
+        self.connect
+    }
+    pub fn get_total(&self) -> Option<Duration> {
+        self.total
+    }
+    pub fn get_first_byte(&self) -> Option<Duration> {
+        self.first_byte
+    }
+    pub fn get_idle(&self) -> Option<Duration> {
+        self.idle
+    }
+    // 用 other 中设置了的项覆盖当前的值
+    pub fn merge(self, other: Timeouts) -> Self {
+        Timeouts {
+            connect: other.connect.or(self.connect),
+            total: other.total.or(self.total),
+            first_byte: other.first_byte.or(self.first_byte),
+            idle: other.idle.or(self.idle),
+        }
+    }
+}
+
+/// 超时的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeoutKind {
+    Connect,
+    Total,
+    FirstByte,
+    Idle,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeoutKind::Connect => write!(f, "连接超时"),
+            TimeoutKind::Total => write!(f, "总超时"),
+            TimeoutKind::FirstByte => write!(f, "首字节超时"),
+            TimeoutKind::Idle => write!(f, "空闲超时"),
+        }
+    }
+}
+
+/// 超时错误。传输层和响应体字节流只能返回 `std::io::Error`，超时时以
+/// `io::Error::new(io::ErrorKind::TimedOut, TimeoutError { .. })` 返回，客户端会把它转换为 `DeepSeekError::Timeout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError {
+    pub kind: TimeoutKind,
+    // 设置的超时时间
+    pub after: Duration,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}（{:?}）", self.kind, self.after)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+impl From<TimeoutError> for io::Error {
+    fn from(err: TimeoutError) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+impl TimeoutError {
+    pub fn new(kind: TimeoutKind, after: Duration) -> Self {
+        TimeoutError { kind, after }
+    }
+    // 从 io::Error 中取出超时错误
+    pub fn from_io(err: &io::Error) -> Option<TimeoutError> {
+        err.get_ref()?.downcast_ref::<TimeoutError>().copied()
+    }
+}
+
+#[cfg(feature = "client")]
+// 总超时的截止时间
+pub(crate) fn deadline(timeouts: &Timeouts) -> Option<(Instant, TimeoutError)> {
+    timeouts
+        .total
+        .map(|total| (Instant::now() + total, TimeoutError::new(TimeoutKind::Total, total)))
+}
+
+#[cfg(feature = "client")]
+// 等待 future，超过截止时间时返回对应的超时错误
+pub(crate) async fn within<T>(
+    future: impl Future<Output = T>,
+    limits: impl IntoIterator<Item = (Instant, TimeoutError)>,
+) -> Result<T, TimeoutError> {
+    match limits.into_iter().min_by_key(|(at, _)| *at) {
+        Some((at, err)) => tokio::time::timeout_at(at, future).await.map_err(|_| err),
+        None => Ok(future.await),
+    }
+}
+
+#[cfg(feature = "client")]
+// 为响应体加上空闲超时和总超时
+pub(crate) fn guard_body(body: ByteStream, idle: Option<Duration>, deadline: Option<(Instant, TimeoutError)>) -> ByteStream {
+    if idle.is_none() && deadline.is_none() {
+        return body;
+    }
+    Box::pin(async_stream::stream! {
+        let mut body = body;
+        loop {
+            let idle = idle.map(|idle| (Instant::now() + idle, TimeoutError::new(TimeoutKind::Idle, idle)));
+            match within(body.next(), idle.into_iter().chain(deadline)).await {
+                Ok(Some(chunk)) => yield chunk,
+                Ok(None) => break,
+                Err(err) => {
+                    yield Err(err.into());
+                    break;
+                }
+            }
+        }
+    })
+}
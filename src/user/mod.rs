@@ -1,5 +1,10 @@
 pub mod request;
 pub mod response;
+#[cfg(feature = "client")]
+pub mod watcher;
 
 pub use request::BalanceRequest;
-pub use response::BalanceResponse;
\ No newline at end of file
+pub use response::{Amount, BalanceInfo, BalanceResponse, Currency};
+pub use rust_decimal::Decimal;
+#[cfg(feature = "client")]
+pub use watcher::{BalanceEvent, BalanceWatcher};
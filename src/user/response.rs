@@ -1,39 +1,166 @@
-use serde::{Deserialize, Serialize};
+use crate::lenient::{null_default, skip_extra, Extra};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// 币种
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Currency {
+    CNY,
+    USD,
+    // 本库不认识的币种
+    Other(String),
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Currency::CNY => write!(f, "CNY"),
+            Currency::USD => write!(f, "USD"),
+            Currency::Other(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_uppercase().as_str() {
+            "CNY" => Currency::CNY,
+            "USD" => Currency::USD,
+            _ => Currency::Other(s.trim().to_string()),
+        })
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Ok(code.parse().unwrap_or_else(|never| match never {}))
+    }
+}
+
+/// 某个币种的金额
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Amount {
+    pub value: Decimal,
+    pub currency: Currency,
+}
+
+impl Amount {
+    pub fn new(value: Decimal, currency: Currency) -> Self {
+        Amount { value, currency }
+    }
+    pub fn cny(value: Decimal) -> Self {
+        Self::new(value, Currency::CNY)
+    }
+    pub fn usd(value: Decimal) -> Self {
+        Self::new(value, Currency::USD)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.currency)
+    }
+}
+
+/// 账户余额
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct BalanceResponse {
+    // 当前账户是否有余额可供 API 调用
+    #[serde(deserialize_with = "null_default")]
     is_available: bool,
-    balance_infos: Vec<BalanceInfos>,
+    // 每个币种的余额
+    #[serde(deserialize_with = "null_default")]
+    balance_infos: Vec<BalanceInfo>,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
 impl BalanceResponse {
     pub fn is_available(&self) -> bool {
         self.is_available
     }
-    pub fn balance_infos(&self) -> &[BalanceInfos] {
+    pub fn balance_infos(&self) -> &[BalanceInfo] {
         &self.balance_infos
     }
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    // 某个币种的余额信息
+    pub fn get(&self, currency: &Currency) -> Option<&BalanceInfo> {
+        self.balance_infos.iter().find(|info| &info.currency == currency)
+    }
+    // 某个币种的总余额，没有该币种时为 0
+    pub fn total_in(&self, currency: &Currency) -> Decimal {
+        self.get(currency).map(|info| info.total_balance).unwrap_or_default()
+    }
+    // 每个币种的总余额
+    pub fn totals(&self) -> Vec<Amount> {
+        self.balance_infos.iter().map(BalanceInfo::total).collect()
+    }
+    // 阈值所在币种的总余额是否低于阈值，响应中没有该币种时为 None
+    pub fn is_below(&self, threshold: &Amount) -> Option<bool> {
+        self.get(&threshold.currency).map(|info| info.total_balance < threshold.value)
+    }
+}
+
+impl fmt::Display for BalanceResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let totals: Vec<String> = self.totals().iter().map(Amount::to_string).collect();
+        write!(f, "{}", totals.join(", "))
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct BalanceInfos {
-    currency: String,
-    total_balance: String,
-    granted_balance: String,
-    topped_up_balance: String,
+/// 某个币种的余额，金额以字符串返回，解析为 [`Decimal`] 以免损失精度。
+/// 币种是必需的，金额缺少或为 null 时为 0
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceInfo {
+    currency: Currency,
+    // 总的可用余额，包括赠金和充值余额
+    #[serde(default, deserialize_with = "null_default")]
+    total_balance: Decimal,
+    // 未过期的赠金余额
+    #[serde(default, deserialize_with = "null_default")]
+    granted_balance: Decimal,
+    // 充值余额
+    #[serde(default, deserialize_with = "null_default")]
+    topped_up_balance: Decimal,
+    // 本库没有定义的字段
+    #[serde(flatten, skip_serializing_if = "skip_extra")]
+    extra: Extra,
 }
 
-impl BalanceInfos {
-    pub fn currency(&self) -> &str {
+impl BalanceInfo {
+    pub fn currency(&self) -> &Currency {
         &self.currency
     }
-    pub fn total_balance(&self) -> &str {
-        &self.total_balance
+    pub fn total_balance(&self) -> Decimal {
+        self.total_balance
     }
-    pub fn granted_balance(&self) -> &str {
-        &self.granted_balance
+    pub fn granted_balance(&self) -> Decimal {
+        self.granted_balance
     }
-    pub fn topped_up_balance(&self) -> &str {
-        &self.topped_up_balance
+    pub fn topped_up_balance(&self) -> Decimal {
+        self.topped_up_balance
     }
-}
\ No newline at end of file
+    pub fn extra(&self) -> &Extra {
+        &self.extra
+    }
+    pub fn total(&self) -> Amount {
+        Amount::new(self.total_balance, self.currency.clone())
+    }
+}
+
+#[deprecated(note = "请使用 BalanceInfo")]
+pub type BalanceInfos = BalanceInfo;
@@ -0,0 +1,135 @@
+//! # 余额监控
+//! 在后台定期查询余额，余额低于阈值或恢复时通过 channel 发出事件。
+//! 响应中没有阈值所在的币种时跳过该阈值，不会当作余额为 0。
+//!
+//! ```no_run
+//! use deepseek_rs::DeepSeekClient;
+//! use deepseek_rs::user::{Amount, BalanceEvent, BalanceWatcher, Decimal};
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let client = DeepSeekClient::new("sk-...");
+//! let mut events = BalanceWatcher::new(client)
+//!     .interval(Duration::from_secs(300))
+//!     .threshold(Amount::cny(Decimal::new(50, 0)))
+//!     .threshold(Amount::cny(Decimal::new(10, 0)))
+//!     .spawn();
+//! while let Some(event) = events.recv().await {
+//!     if let BalanceEvent::Low { threshold, balance } = event {
+//!         eprintln!("余额 {} 低于 {}", balance, threshold);
+//!     }
+//! }
+//! # }
+//! ```
+use super::response::Amount;
+use crate::client::DeepSeekClient;
+use crate::error::DeepSeekError;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 默认的查询间隔
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 余额事件
+#[derive(Debug)]
+pub enum BalanceEvent {
+    // 余额从不低于阈值变为低于阈值，每次跌破只发一次；第一次查询时已经低于阈值也会发出
+    Low { threshold: Amount, balance: Amount },
+    // 余额回到阈值以上，例如充值之后
+    Recovered { threshold: Amount, balance: Amount },
+    // 账户变为不可用（is_available 为 false）
+    Unavailable,
+    // 查询失败，下一次仍会继续查询
+    Error(DeepSeekError),
+}
+
+/// 余额监控，用 [`BalanceWatcher::spawn`] 在后台运行
+#[derive(Debug, Clone)]
+pub struct BalanceWatcher {
+    client: DeepSeekClient,
+    interval: Duration,
+    thresholds: Vec<Amount>,
+    // channel 的容量
+    capacity: usize,
+}
+
+impl BalanceWatcher {
+    pub fn new(client: DeepSeekClient) -> Self {
+        BalanceWatcher {
+            client,
+            interval: DEFAULT_WATCH_INTERVAL,
+            thresholds: Vec::new(),
+            capacity: 16,
+        }
+    }
+    // 查询间隔，至少为 1 毫秒
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_millis(1));
+        self
+    }
+    // 添加阈值，可以为不同币种设置多个阈值
+    pub fn threshold(mut self, threshold: Amount) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// 在后台开始监控，立即查询一次，之后每隔 interval 查询一次；接收端被丢弃后停止
+    pub fn spawn(self) -> mpsc::Receiver<BalanceEvent> {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // 每个阈值上一次是否低于阈值
+            let mut below: Vec<Option<bool>> = vec![None; self.thresholds.len()];
+            let mut available = true;
+            loop {
+                tokio::select! {
+                    _ = sender.closed() => return,
+                    _ = ticker.tick() => {}
+                }
+                let balance = match self.client.balance().await {
+                    Ok(balance) => balance,
+                    Err(err) => {
+                        if sender.send(BalanceEvent::Error(err)).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+                let mut events = Vec::new();
+                if available && !balance.is_available() {
+                    events.push(BalanceEvent::Unavailable);
+                }
+                available = balance.is_available();
+                for (threshold, was_below) in self.thresholds.iter().zip(below.iter_mut()) {
+                    let Some(is_below) = balance.is_below(threshold) else {
+                        continue;
+                    };
+                    let current = Amount::new(balance.total_in(&threshold.currency), threshold.currency.clone());
+                    match (*was_below, is_below) {
+                        (None | Some(false), true) => events.push(BalanceEvent::Low {
+                            threshold: threshold.clone(),
+                            balance: current,
+                        }),
+                        (Some(true), false) => events.push(BalanceEvent::Recovered {
+                            threshold: threshold.clone(),
+                            balance: current,
+                        }),
+                        _ => {}
+                    }
+                    *was_below = Some(is_below);
+                }
+                for event in events {
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
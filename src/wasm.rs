@@ -0,0 +1,154 @@
+//! # wasm 客户端
+//! 需要启用 `wasm` 特性。[`WasmClient`] 在 `wasm32-unknown-unknown` 上通过浏览器或边缘运行时的 fetch 发送请求，
+//! 与原生客户端共用请求、响应类型和流式响应的解析（[`crate::sse::JsonEventDecoder`]），不依赖 tokio。
+//! 在原生平台上同样可以编译和使用（通过 reqwest 发送），便于在本地调试。
+//!
+//! 与 [`crate::DeepSeekClient`] 相比没有中间件、重试、限流和超时；请求上设置的超时会被忽略，
+//! 丢弃返回的 future 或流即可中止请求。
+//!
+//! ```toml
+//! deepseekClient-rs = { version = "0.1", default-features = false, features = ["wasm"] }
+//! ```
+//!
+//! ```no_run
+//! use deepseek_rs::chat::*;
+//! use deepseek_rs::wasm::WasmClient;
+//! use futures::StreamExt;
+//!
+//! # async fn run() -> Result<(), deepseek_rs::DeepSeekError> {
+//! // 浏览器中不要直接暴露 API Key，通常把 base_url 指向自己的代理
+//! let client = WasmClient::new("sk-...").base_url("https://example.com/deepseek");
+//! let (_, request) = ChatRequestBuilder::new()
+//!     .add_message(Message::user_message("你好"))
+//!     .build();
+//! let mut stream = client.chat_stream(&request).await?;
+//! while let Some(chunk) = stream.next().await {
+//!     print!("{}", chunk?.content().concat());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::auth::SecretKey;
+use crate::base_types::endpoint::{
+    BALANCE_PATH, CHAT_COMPLETIONS_PATH, DEFAULT_BASE_URL, FIM_COMPLETIONS_PATH, MODELS_PATH,
+};
+use crate::chat::{ChatCompletionChunk, ChatRequest, ChatResponse};
+use crate::completions::{FimRequest, FimResponse};
+use crate::error::DeepSeekError;
+use crate::model::ModelResponse;
+use crate::sse::JsonEventDecoder;
+use crate::user::BalanceResponse;
+use futures::StreamExt;
+use futures::stream::LocalBoxStream;
+use serde::de::DeserializeOwned;
+use std::io::Error;
+
+/// wasm 上的流式响应，fetch 返回的流不能跨线程
+pub type LocalStream<T> = LocalBoxStream<'static, Result<T, DeepSeekError>>;
+
+/// 基于 fetch 的客户端，clone 的开销很小
+#[derive(Debug, Clone)]
+pub struct WasmClient {
+    api_key: SecretKey,
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl WasmClient {
+    pub fn new(api_key: &str) -> Self {
+        WasmClient {
+            api_key: SecretKey::new(api_key),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+    // 设置接口地址，例如转发请求的代理
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    // 发送 chat 请求，请求中的 stream 会被设置为 false
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, DeepSeekError> {
+        let mut request = request.clone();
+        request.set_stream(false);
+        self.json(self.post(CHAT_COMPLETIONS_PATH, request.to_json()?)).await
+    }
+    // 发送流式 chat 请求，请求中的 stream 会被设置为 true
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<LocalStream<ChatCompletionChunk>, DeepSeekError> {
+        let mut request = request.clone();
+        request.set_stream(true);
+        self.stream(self.post(CHAT_COMPLETIONS_PATH, request.to_json()?)).await
+    }
+    // 发送 FIM 补全请求，请求中的 stream 会被设置为 false
+    pub async fn fim(&self, request: &FimRequest) -> Result<FimResponse, DeepSeekError> {
+        let mut request = request.clone();
+        request.set_stream(false);
+        self.json(self.post(FIM_COMPLETIONS_PATH, request.to_json()?)).await
+    }
+    // 发送流式 FIM 补全请求，请求中的 stream 会被设置为 true
+    pub async fn fim_stream(&self, request: &FimRequest) -> Result<LocalStream<FimResponse>, DeepSeekError> {
+        let mut request = request.clone();
+        request.set_stream(true);
+        self.stream(self.post(FIM_COMPLETIONS_PATH, request.to_json()?)).await
+    }
+    // 列出可用的模型，不缓存
+    pub async fn models(&self) -> Result<ModelResponse, DeepSeekError> {
+        self.json(self.http.get(self.url(MODELS_PATH))).await
+    }
+    // 查询账户余额
+    pub async fn balance(&self) -> Result<BalanceResponse, DeepSeekError> {
+        self.json(self.http.get(self.url(BALANCE_PATH))).await
+    }
+
+    fn post(&self, path: &str, body: String) -> reqwest::RequestBuilder {
+        self.http
+            .post(self.url(path))
+            .header("Content-Type", "application/json")
+            .body(body)
+    }
+
+    // 添加鉴权请求头并发送，非 2xx 响应返回 [`DeepSeekError::Api`]
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, DeepSeekError> {
+        let response = request
+            .header("Authorization", format!("Bearer {}", self.api_key.expose()))
+            .send()
+            .await
+            .map_err(Error::other)?;
+        let status = response.status().as_u16();
+        if !(200..300).contains(&status) {
+            let body = response.text().await.map_err(Error::other)?;
+            return Err(DeepSeekError::api(status, body));
+        }
+        Ok(response)
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, DeepSeekError> {
+        let text = self.send(request).await?.text().await.map_err(Error::other)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    async fn stream<T: DeserializeOwned + 'static>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<LocalStream<T>, DeepSeekError> {
+        let mut body = self.send(request).await?.bytes_stream();
+        Ok(async_stream::try_stream! {
+            let mut decoder = JsonEventDecoder::<T>::new();
+            while let Some(bytes) = body.next().await {
+                let bytes = bytes.map_err(Error::other)?;
+                for item in decoder.feed(&bytes) {
+                    yield item?;
+                }
+                if decoder.is_done() {
+                    break;
+                }
+            }
+            decoder.finish()?;
+        }
+        .boxed_local())
+    }
+}
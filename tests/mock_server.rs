@@ -0,0 +1,827 @@
+//! 使用 `testing` 特性的模拟服务器测试客户端，不需要网络和 API Key：
+//! `cargo test --features testing`
+use deepseek_rs::DeepSeekClient;
+use deepseek_rs::auth::{FileKey, KeyPool, PoolStrategy, RotatingKey};
+use deepseek_rs::cancel::CancelToken;
+#[cfg(feature = "batch")]
+use deepseek_rs::batch::{BatchInput, BatchRunner};
+#[cfg(feature = "cache")]
+use deepseek_rs::cache::{CachedClient, MemoryStore};
+use deepseek_rs::chat::*;
+use deepseek_rs::completions::FimRequestBuilder;
+use deepseek_rs::error::DeepSeekError;
+use deepseek_rs::http::{Cassette, HttpRequest, HttpResponse, RecordTransport, ReplayTransport, ReqwestTransport};
+use deepseek_rs::middleware::{LoggingLayer, Middleware, Next, RequestContext, RequestIdLayer};
+use deepseek_rs::provider::{AuthScheme, Provider};
+use deepseek_rs::rate_limit::{RateLimitLayer, RateLimiter};
+use deepseek_rs::retry::RetryPolicy;
+use deepseek_rs::testing::{MockResponse, MockServer, Route, fixtures};
+use deepseek_rs::timeout::{TimeoutKind, Timeouts};
+use deepseek_rs::user::{Amount, BalanceEvent, BalanceWatcher, Currency};
+use deepseek_rs::lenient::Drift;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+async fn setup() -> (MockServer, DeepSeekClient) {
+    let server = MockServer::start().await.unwrap();
+    let client = DeepSeekClient::new("sk-test").base_url(&server.url());
+    (server, client)
+}
+
+fn request(content: &str) -> ChatRequest {
+    ChatRequestBuilder::new().add_message(Message::user_message(content)).build().1
+}
+
+// 读完流式响应，返回拼接的内容和最后一个 chunk 的 finish_reason
+async fn collect(mut stream: ChatStream) -> (String, Option<String>) {
+    let mut content = String::new();
+    let mut finish_reason = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.unwrap();
+        content.push_str(&chunk.content().concat());
+        if let Some(reason) = chunk.choices().first().and_then(|c| c.finish_reason()) {
+            finish_reason = Some(reason.to_string());
+        }
+    }
+    (content, finish_reason)
+}
+
+#[tokio::test]
+async fn chat() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！"));
+    let response = client.chat(&request("你好")).await.unwrap();
+    assert_eq!(response.content(), vec!["你好！"]);
+    assert_eq!(response.choices[0].finish_reason(), Some("stop"));
+    assert_eq!(response.usage().unwrap().prompt_tokens(), 10);
+
+    let requests = server.requests_to(Route::ChatCompletions);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method(), "POST");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer sk-test"));
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["model"], "deepseek-chat");
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["messages"][0]["content"], "你好");
+}
+
+#[tokio::test]
+async fn chat_stream() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("流式回复"));
+    let stream = client.chat_stream(&request("你好")).await.unwrap();
+    let (content, finish_reason) = collect(stream).await;
+    assert_eq!(content, "流式回复");
+    assert_eq!(finish_reason.as_deref(), Some("stop"));
+    assert_eq!(server.requests_to(Route::ChatCompletions)[0].json().unwrap()["stream"], true);
+}
+
+#[tokio::test]
+async fn truncated_stream() {
+    let (server, client) = setup().await;
+    // 多字节字符的 chunk 在中途断开
+    let chunks = fixtures::chat_chunks("你好世界，这是一段很长的中文回复");
+    server.push(Route::ChatCompletions, MockResponse::sse(chunks).truncated());
+    let mut stream = client.chat_stream(&request("你好")).await.unwrap();
+    let mut received = String::new();
+    let mut error = None;
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => received.push_str(&chunk.content().concat()),
+            Err(err) => {
+                error = Some(err);
+                break;
+            }
+        }
+    }
+    assert!(!received.is_empty());
+    assert!("你好世界，这是一段很长的中文回复".starts_with(&received));
+    assert!(error.is_some(), "断开的流应当返回错误");
+}
+
+#[tokio::test]
+async fn retries_after_429() {
+    let (server, client) = setup().await;
+    let client = client.retry(RetryPolicy::new().max_retries(2).base_delay(Duration::from_millis(10)));
+    server.push(Route::ChatCompletions, MockResponse::error(429, "Rate limit reached"));
+    server.push(Route::ChatCompletions, MockResponse::chat("重试成功"));
+    let response = client.chat(&request("你好")).await.unwrap();
+    assert_eq!(response.content(), vec!["重试成功"]);
+    assert_eq!(server.requests_to(Route::ChatCompletions).len(), 2);
+}
+
+#[tokio::test]
+async fn truncated_event_is_cut_on_char_boundary() {
+    let (server, client) = setup().await;
+    // 第二个事件在多字节字符中间断开
+    let events = vec![json!({"content": "你好"}), json!({"content": "中中中中中中中中"})];
+    server.push(Route::ChatCompletions, MockResponse::sse(events).truncated());
+    let response = client.send(HttpRequest::post(&server.url_for(Route::ChatCompletions), String::new())).await.unwrap();
+    let mut body = response.into_body();
+    let mut received = Vec::new();
+    let mut error = None;
+    while let Some(chunk) = body.next().await {
+        match chunk {
+            Ok(chunk) => received.extend_from_slice(&chunk),
+            Err(err) => {
+                error = Some(err);
+                break;
+            }
+        }
+    }
+    assert!(error.is_some(), "断开的流应当返回错误");
+    // 收到了完整的事件和断开前的半个事件
+    let received = String::from_utf8_lossy(&received);
+    assert!(received.starts_with("data: {\"content\":\"你好\"}\n\ndata: {\"content\":\"中"));
+    assert!(!received.ends_with("\n\n"));
+}
+
+#[tokio::test]
+async fn error_status() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::rate_limited());
+    let err = client.chat(&request("你好")).await.unwrap_err();
+    assert_eq!(err.status(), Some(429));
+    match err {
+        DeepSeekError::Api { message, .. } => assert_eq!(message, "Rate limit reached"),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn malformed_json() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::malformed_json());
+    let err = client.chat(&request("你好")).await.unwrap_err();
+    assert!(matches!(err, DeepSeekError::Json(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn models() {
+    let (server, client) = setup().await;
+    let models = client.models().await.unwrap();
+    let ids: Vec<&str> = models.data().iter().map(|m| m.id()).collect();
+    assert_eq!(ids, vec!["deepseek-chat", "deepseek-reasoner"]);
+    assert_eq!(server.requests_to(Route::Models)[0].method(), "GET");
+}
+
+#[tokio::test]
+async fn balance() {
+    let (server, client) = setup().await;
+    let balance = client.balance().await.unwrap();
+    assert!(balance.is_available());
+    assert!(!balance.balance_infos().is_empty());
+    assert_eq!(server.requests_to(Route::Balance).len(), 1);
+}
+
+#[tokio::test]
+async fn fim_usage_reconciles_rate_limit() {
+    let (server, client) = setup().await;
+    // 每次预扣 4000 个 token，不按实际用量对账时第二次请求要等待半分钟以上
+    let limiter = Arc::new(RateLimiter::new().tokens_per_minute(5000).default_max_tokens(4000));
+    let client = client.layer(RateLimitLayer::new(limiter));
+    let (_, request) = FimRequestBuilder::new("def fib(").build();
+    for _ in 0..3 {
+        let response = tokio::time::timeout(Duration::from_secs(5), client.fim(&request))
+            .await
+            .expect("FIM 的预扣额度没有对账")
+            .unwrap();
+        assert!(response.usage().is_some());
+    }
+    assert_eq!(server.requests_to(Route::FimCompletions).len(), 3);
+}
+
+#[tokio::test]
+async fn cassette_record_and_replay() {
+    let (server, client) = setup().await;
+    let path = std::env::temp_dir().join(format!("deepseek-replay-{}.json", std::process::id()));
+    let client = client.transport(RecordTransport::new(ReqwestTransport::new(), &path));
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！"));
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("流式回复").chunk_interval(Duration::from_millis(40)));
+    client.chat(&request("你好")).await.unwrap();
+    let (_, stream_request) = ChatRequestBuilder::new().add_message(Message::user_message("流式")).stream(true).build();
+    collect(client.chat_stream(&stream_request).await.unwrap()).await;
+
+    // 请求体换成带缩进的 JSON，回放时仍然匹配
+    let mut file: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    for interaction in file["interactions"].as_array_mut().unwrap() {
+        let body: serde_json::Value = serde_json::from_str(interaction["request"]["body"].as_str().unwrap()).unwrap();
+        interaction["request"]["body"] = json!(serde_json::to_string_pretty(&body).unwrap());
+    }
+    std::fs::write(&path, file.to_string()).unwrap();
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let recorded = cassette.interactions()[1].response();
+    assert!(!recorded.is_incomplete());
+    let delay: Duration = recorded.chunks().iter().map(|c| c.delay()).sum();
+    assert!(recorded.chunks().iter().skip(1).any(|c| c.delay() >= Duration::from_millis(30)));
+
+    // base url 不同也按路径回放，不访问网络
+    let replay = DeepSeekClient::new("sk-test")
+        .base_url("http://127.0.0.1:9")
+        .transport(ReplayTransport::new(cassette).timing(true));
+    let response = replay.chat(&request("你好")).await.unwrap();
+    assert_eq!(response.content(), vec!["你好！"]);
+    let started = std::time::Instant::now();
+    let (content, _) = collect(replay.chat_stream(&stream_request).await.unwrap()).await;
+    assert_eq!(content, "流式回复");
+    assert!(started.elapsed() >= delay.saturating_sub(Duration::from_millis(20)));
+    // 请求体或方法、路径不同时没有匹配的记录
+    assert!(replay.chat(&request("别的问题")).await.is_err());
+    assert!(replay.models().await.is_err());
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn cassette_marks_dropped_stream_incomplete() {
+    let (server, client) = setup().await;
+    let path = std::env::temp_dir().join(format!("deepseek-incomplete-{}.json", std::process::id()));
+    let client = client.transport(RecordTransport::new(ReqwestTransport::new(), &path));
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("很长的流式回复").chunk_interval(Duration::from_millis(50)));
+    let mut stream = client.chat_stream(&request("你好")).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    drop(stream);
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(cassette.interactions()[0].response().is_incomplete());
+
+    let replay = DeepSeekClient::new("sk-test").transport(ReplayTransport::new(cassette));
+    let mut stream = replay.chat_stream(&request("你好")).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    let mut last = None;
+    while let Some(chunk) = stream.next().await {
+        last = Some(chunk);
+    }
+    assert!(matches!(last, Some(Err(_))));
+}
+
+// 模拟服务器收到的每个 chat 请求的 Authorization 请求头
+fn authorizations(server: &MockServer) -> Vec<String> {
+    server
+        .requests_to(Route::ChatCompletions)
+        .iter()
+        .map(|r| r.header("authorization").unwrap_or_default().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn key_pool_fails_over_on_rejected_key() {
+    let server = MockServer::start().await.unwrap();
+    let pool = Arc::new(KeyPool::new(["sk-a", "sk-b"]).strategy(PoolStrategy::Failover));
+    let client = DeepSeekClient::with_key_provider(pool.clone()).base_url(&server.url());
+    server.push(Route::ChatCompletions, MockResponse::error(401, "invalid key"));
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！"));
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(pool.available(), 1);
+    // 换 key 重试之后，停用的 key 不再使用
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(authorizations(&server), vec!["Bearer sk-a", "Bearer sk-b", "Bearer sk-b"]);
+
+    // 所有 key 都被拒绝时返回错误，不再重试
+    server.clear_requests();
+    server.push(Route::ChatCompletions, MockResponse::error(402, "insufficient balance"));
+    let err = client.chat(&request("你好")).await.unwrap_err();
+    assert_eq!(err.status(), Some(402));
+    assert_eq!(pool.available(), 0);
+    assert_eq!(authorizations(&server), vec!["Bearer sk-b"]);
+    assert!(matches!(client.chat(&request("你好")).await, Err(DeepSeekError::Auth(_))));
+
+    pool.reset();
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(authorizations(&server).last().map(String::as_str), Some("Bearer sk-a"));
+}
+
+#[tokio::test]
+async fn key_pool_round_robin() {
+    let server = MockServer::start().await.unwrap();
+    let pool = Arc::new(KeyPool::new(["sk-a", "sk-b", "sk-c"]));
+    let client = DeepSeekClient::with_key_provider(pool.clone()).base_url(&server.url());
+    for _ in 0..3 {
+        client.chat(&request("你好")).await.unwrap();
+    }
+    // 被拒绝的 key 在轮询中跳过，其他状态码不会停用 key
+    server.push(Route::ChatCompletions, MockResponse::error(401, "invalid key"));
+    client.chat(&request("你好")).await.unwrap();
+    server.push(Route::ChatCompletions, MockResponse::error(400, "bad request"));
+    assert!(client.chat(&request("你好")).await.is_err());
+    for _ in 0..2 {
+        client.chat(&request("你好")).await.unwrap();
+    }
+    assert_eq!(
+        authorizations(&server),
+        vec!["Bearer sk-a", "Bearer sk-b", "Bearer sk-c", "Bearer sk-a", "Bearer sk-b", "Bearer sk-c", "Bearer sk-b", "Bearer sk-c"]
+    );
+    assert_eq!(pool.available(), 2);
+}
+
+#[tokio::test]
+async fn single_key_is_not_retried_on_401() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::error(401, "invalid key"));
+    assert_eq!(client.chat(&request("你好")).await.unwrap_err().status(), Some(401));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn rotating_and_file_keys_are_read_per_request() {
+    let server = MockServer::start().await.unwrap();
+    let key = RotatingKey::new("sk-old");
+    let client = DeepSeekClient::with_key_provider(key.clone()).base_url(&server.url());
+    client.chat(&request("你好")).await.unwrap();
+    key.rotate("sk-new");
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(authorizations(&server), vec!["Bearer sk-old", "Bearer sk-new"]);
+
+    server.clear_requests();
+    let path = std::env::temp_dir().join(format!("deepseek-key-{}", std::process::id()));
+    std::fs::write(&path, "sk-file-1\n").unwrap();
+    let client = DeepSeekClient::with_key_provider(FileKey::new(&path)).base_url(&server.url());
+    client.chat(&request("你好")).await.unwrap();
+    std::fs::write(&path, "sk-file-2\n").unwrap();
+    // 修改时间精度可能不足，明确设置一个不同的时间
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(std::time::SystemTime::now() + Duration::from_secs(10)).unwrap();
+    client.chat(&request("你好")).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(client.chat(&request("你好")).await, Err(DeepSeekError::Auth(_))));
+    assert_eq!(authorizations(&server), vec!["Bearer sk-file-1", "Bearer sk-file-2"]);
+}
+
+// 不重试、使用给定超时的客户端
+async fn setup_timeouts(timeouts: Timeouts) -> (MockServer, DeepSeekClient) {
+    let (server, client) = setup().await;
+    (server, client.retry(RetryPolicy::none()).timeouts(timeouts))
+}
+
+#[tokio::test]
+async fn connect_timeout() {
+    // backlog 为 0 且已经有一个连接在等待 accept，之后的 SYN 会被丢弃，连接一直建立不起来
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let listener = socket.listen(0).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut pending = Vec::new();
+    while let Ok(Ok(stream)) = tokio::time::timeout(Duration::from_millis(200), tokio::net::TcpStream::connect(addr)).await {
+        pending.push(stream);
+    }
+    let client = DeepSeekClient::new("sk-test")
+        .base_url(&format!("http://{}", addr))
+        .retry(RetryPolicy::none())
+        .timeouts(Timeouts::new().connect(Duration::from_millis(200)));
+    let err = client.chat(&request("你好")).await.unwrap_err();
+    assert!(matches!(err, DeepSeekError::Timeout { kind: TimeoutKind::Connect, after } if after == Duration::from_millis(200)));
+}
+
+#[tokio::test]
+async fn first_byte_timeout() {
+    let (server, client) = setup_timeouts(Timeouts::new().first_byte(Duration::from_millis(100))).await;
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！").latency(Duration::from_millis(500)));
+    let err = client.chat(&request("你好")).await.unwrap_err();
+    assert_eq!(err.timeout_kind(), Some(TimeoutKind::FirstByte));
+}
+
+#[tokio::test]
+async fn total_timeout_covers_stream_body() {
+    let (server, client) = setup_timeouts(Timeouts::new().total(Duration::from_millis(250))).await;
+    let chunks = fixtures::chat_chunks("一段需要很久才能发完的流式回复");
+    server.push(Route::ChatCompletions, MockResponse::sse(chunks).chunk_interval(Duration::from_millis(50)));
+    // 响应头很快到达，超时发生在读取响应体时
+    let mut stream = client.chat_stream(&request("你好")).await.unwrap();
+    let err = loop {
+        match stream.next().await {
+            Some(Ok(_)) => continue,
+            Some(Err(err)) => break err,
+            None => panic!("流没有超时"),
+        }
+    };
+    assert_eq!(err.timeout_kind(), Some(TimeoutKind::Total));
+}
+
+#[tokio::test]
+async fn idle_timeout_between_chunks() {
+    let (server, client) = setup_timeouts(Timeouts::new().idle(Duration::from_millis(100))).await;
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("流式回复").chunk_interval(Duration::from_millis(300)));
+    let mut stream = client.chat_stream(&request("你好")).await.unwrap();
+    let err = loop {
+        match stream.next().await {
+            Some(Ok(_)) => continue,
+            Some(Err(err)) => break err,
+            None => panic!("流没有超时"),
+        }
+    };
+    assert!(matches!(err, DeepSeekError::Timeout { kind: TimeoutKind::Idle, .. }));
+    assert_eq!(err.timeout_kind(), Some(TimeoutKind::Idle));
+}
+
+#[tokio::test]
+async fn request_timeouts_override_client() {
+    let (server, client) = setup_timeouts(Timeouts::new().first_byte(Duration::from_millis(100))).await;
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！").latency(Duration::from_millis(300)));
+    let (_, slow) = ChatRequestBuilder::new()
+        .add_message(Message::user_message("你好"))
+        .timeouts(Timeouts::new().first_byte(Duration::from_secs(5)))
+        .build();
+    client.chat(&slow).await.unwrap();
+
+    let (server, client) = setup_timeouts(Timeouts::new()).await;
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！").latency(Duration::from_millis(300)));
+    let (_, strict) = ChatRequestBuilder::new()
+        .add_message(Message::user_message("你好"))
+        .timeouts(Timeouts::new().first_byte(Duration::from_millis(100)))
+        .build();
+    assert_eq!(client.chat(&strict).await.unwrap_err().timeout_kind(), Some(TimeoutKind::FirstByte));
+    // 不是超时的错误没有 kind
+    server.push(Route::ChatCompletions, MockResponse::error(400, "bad request"));
+    assert_eq!(client.chat(&request("你好")).await.unwrap_err().timeout_kind(), None);
+}
+
+// 带取消令牌的请求
+fn cancellable(content: &str, token: &CancelToken) -> ChatRequest {
+    ChatRequestBuilder::new()
+        .add_message(Message::user_message(content))
+        .cancel_token(token.clone())
+        .build()
+        .1
+}
+
+// 等待服务器发现客户端断开连接
+async fn wait_disconnects(server: &MockServer, count: usize) {
+    let waited = tokio::time::timeout(Duration::from_secs(2), async {
+        while server.disconnects() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(waited.is_ok(), "服务器只看到 {} 个断开的连接", server.disconnects());
+}
+
+#[tokio::test]
+async fn cancel_before_response() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！").latency(Duration::from_secs(2)));
+    let token = CancelToken::new();
+    let stop = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stop.cancel();
+    });
+    let started = std::time::Instant::now();
+    let result = client.chat(&cancellable("你好", &token)).await;
+    assert!(matches!(result, Err(DeepSeekError::Cancelled)));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    wait_disconnects(&server, 1).await;
+}
+
+#[tokio::test]
+async fn cancel_mid_stream_keeps_partial_response() {
+    let (server, client) = setup().await;
+    let content = "一段会被中途取消的很长的流式回复";
+    server.push(Route::ChatCompletions, MockResponse::chat_stream(content).chunk_interval(Duration::from_millis(50)));
+    let token = CancelToken::new();
+    let mut stream = client.chat_stream(&cancellable("你好", &token)).await.unwrap();
+    let mut accumulator = ChatAccumulator::new();
+    for _ in 0..3 {
+        accumulator.push(&stream.next().await.unwrap().unwrap());
+    }
+    token.cancel();
+    // 取消之后不再读取，连接也会在后台关闭
+    wait_disconnects(&server, 1).await;
+    assert!(matches!(stream.next().await, Some(Err(DeepSeekError::Cancelled))));
+
+    let response = accumulator.into_response().unwrap();
+    assert_eq!(response.choices[0].finish_reason(), Some(FINISH_REASON_CANCELLED));
+
+    // collect 在取消时返回已经收到的部分
+    server.push(Route::ChatCompletions, MockResponse::chat_stream(content).chunk_interval(Duration::from_millis(50)));
+    let token = CancelToken::new();
+    let stream = client.chat_stream(&cancellable("你好", &token)).await.unwrap();
+    let stop = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(180)).await;
+        stop.cancel();
+    });
+    let response = ChatAccumulator::collect(stream).await.unwrap();
+    let partial = response.content()[0];
+    assert!(!partial.is_empty() && partial.len() < content.len() && content.starts_with(partial));
+    assert!(response.choices[0].is_cancelled());
+    assert!(response.usage().is_none());
+    wait_disconnects(&server, 2).await;
+}
+
+#[tokio::test]
+async fn chat_json_uses_json_mode() {
+    let (server, client) = setup().await;
+    server.push(Route::ChatCompletions, MockResponse::chat(r#"{"answer": 42}"#));
+    let value: serde_json::Value = client.chat_json(&request("用 json 回答")).await.unwrap();
+    assert_eq!(value, json!({"answer": 42}));
+    assert_eq!(server.requests()[0].json().unwrap()["response_format"], json!({"type": "json_object"}));
+
+    // 没有内容时返回专门的错误，而不是解析占位文本
+    for content in [json!(null), json!("  \n")] {
+        let mut response = fixtures::chat_response("");
+        response["choices"][0]["message"]["content"] = content;
+        response["choices"][0]["finish_reason"] = json!("length");
+        server.push(Route::ChatCompletions, MockResponse::json(response));
+        match client.chat_json::<serde_json::Value>(&request("用 json 回答")).await {
+            Err(DeepSeekError::EmptyContent { finish_reason }) => assert_eq!(finish_reason.as_deref(), Some("length")),
+            other => panic!("{:?}", other),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_client() {
+    // 模拟服务器运行在单独的运行时中，测试本身是同步的
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start()).unwrap();
+    let client = deepseek_rs::blocking::DeepSeekClient::from_client(DeepSeekClient::new("sk-test").base_url(&server.url()));
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！"));
+    assert_eq!(client.chat(&request("你好")).unwrap().content(), vec!["你好！"]);
+
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("流式回复"));
+    let mut accumulator = ChatAccumulator::new();
+    for chunk in client.chat_stream(&request("你好")).unwrap() {
+        accumulator.push(&chunk.unwrap());
+    }
+    assert_eq!(accumulator.into_response().unwrap().content(), vec!["流式回复"]);
+
+    // 提前丢弃迭代器会关闭连接
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("很长的流式回复").chunk_interval(Duration::from_millis(50)));
+    let mut chunks = client.chat_stream(&request("你好")).unwrap();
+    chunks.next().unwrap().unwrap();
+    drop(chunks);
+    runtime.block_on(wait_disconnects(&server, 1));
+    let requests = server.requests_to(Route::ChatCompletions);
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].json().unwrap()["stream"], true);
+}
+
+// 把每个回调记录到共享日志中的中间件
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Middleware for Recorder {
+    fn on_chat_request(&self, request: &mut ChatRequest, _ctx: &RequestContext) {
+        self.log.lock().unwrap().push(format!("{} request", self.name));
+        request.add_message(Message::user_message(self.name));
+    }
+    fn handle<'a>(&'a self, mut request: HttpRequest, next: Next<'a>) -> BoxFuture<'a, Result<HttpResponse, std::io::Error>> {
+        Box::pin(async move {
+            self.log.lock().unwrap().push(format!("{} before", self.name));
+            request.set_header(&format!("x-{}", self.name), next.context().request_id());
+            let response = next.run(request).await;
+            self.log.lock().unwrap().push(format!("{} after", self.name));
+            response
+        })
+    }
+    fn on_chat_response(&self, _response: &mut ChatResponse, _ctx: &RequestContext) {
+        self.log.lock().unwrap().push(format!("{} response", self.name));
+    }
+    fn on_chunk(&self, chunk: &mut ChatCompletionChunk, _ctx: &RequestContext) {
+        if let Some(content) = chunk.content().first().filter(|c| !c.is_empty()) {
+            self.log.lock().unwrap().push(format!("{} chunk {}", self.name, content));
+        }
+    }
+}
+
+#[tokio::test]
+async fn middleware_chain_runs_in_order() {
+    let (server, client) = setup().await;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = lines.clone();
+    let client = client
+        .layer(RequestIdLayer::new())
+        .layer(Recorder { name: "outer", log: log.clone() })
+        .layer(Recorder { name: "inner", log: log.clone() })
+        .layer(LoggingLayer::new(move |line| sink.lock().unwrap().push(line.to_string())));
+    server.push(Route::ChatCompletions, MockResponse::chat("你好！"));
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["outer request", "inner request", "outer before", "inner before", "inner after", "outer after", "outer response", "inner response"]
+    );
+
+    // 中间件对 ChatRequest 和 HTTP 请求的修改都会发出去，请求 ID 在各层之间一致
+    let captured = &server.requests_to(Route::ChatCompletions)[0];
+    let contents: Vec<String> = captured.json().unwrap()["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(contents, vec!["你好", "outer", "inner"]);
+    let request_id = captured.header("x-request-id").unwrap();
+    assert_eq!(captured.header("x-outer"), Some(request_id));
+    assert_eq!(captured.header("x-inner"), Some(request_id));
+    let lines = lines.lock().unwrap().clone();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| line.contains(&format!("request_id={}", request_id))));
+    assert!(lines[0].to_ascii_lowercase().contains("authorization: [redacted]") && !lines[0].contains("sk-test"));
+
+    log.lock().unwrap().clear();
+    server.push(Route::ChatCompletions, MockResponse::chat_stream("流式"));
+    collect(client.chat_stream(&request("你好")).await.unwrap()).await;
+    let chunks: Vec<String> = log.lock().unwrap().iter().filter(|l| l.contains("chunk")).cloned().collect();
+    assert_eq!(chunks, vec!["outer chunk 流", "inner chunk 流", "outer chunk 式", "inner chunk 式"]);
+}
+
+#[tokio::test]
+async fn request_id_layer_keeps_upstream_id() {
+    let (server, client) = setup().await;
+    let client = client.layer(RequestIdLayer::from_fn(|| Some(String::from("upstream-1"))).header_name("X-Trace-Id"));
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(server.requests()[0].header("x-trace-id"), Some("upstream-1"));
+}
+
+#[cfg(feature = "batch")]
+#[tokio::test]
+async fn batch_resume_retries_failed_items() {
+    let (server, client) = setup().await;
+    let dir = std::env::temp_dir().join(format!("deepseek-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (input, output) = (dir.join("input.jsonl"), dir.join("output.jsonl"));
+    let lines: Vec<String> = ["q1", "q2"]
+        .iter()
+        .map(|id| serde_json::to_string(&BatchInput::request(id, request("你好"))).unwrap())
+        .collect();
+    std::fs::write(&input, lines.join("\n")).unwrap();
+    std::fs::write(
+        &output,
+        "{\"custom_id\":\"q1\",\"response\":{}}\n{\"custom_id\":\"q2\",\"error\":{\"status\":429,\"message\":\"rate limited\"}}\n",
+    )
+    .unwrap();
+    let summary = BatchRunner::new(client).run(&input, &output).await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!((summary.skipped, summary.succeeded, summary.failed), (1, 1, 0));
+    assert_eq!(server.requests_to(Route::ChatCompletions).len(), 1);
+}
+
+#[cfg(feature = "cache")]
+#[tokio::test]
+async fn cache_is_keyed_by_base_url() {
+    let (first, client) = setup().await;
+    let (second, _) = setup().await;
+    let store = Arc::new(MemoryStore::new(10));
+    let cached = CachedClient::new(client.clone(), store.clone());
+    let other = CachedClient::new(client.base_url(&second.url()), store);
+    for _ in 0..2 {
+        cached.chat(&request("你好")).await.unwrap();
+        other.chat(&request("你好")).await.unwrap();
+    }
+    assert_eq!(first.requests_to(Route::ChatCompletions).len(), 1);
+    assert_eq!(second.requests_to(Route::ChatCompletions).len(), 1);
+}
+
+// 把计数器和直方图按 `名称{标签}` 保存下来的 metrics recorder
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct TestRecorder(Arc<Mutex<std::collections::BTreeMap<String, f64>>>);
+
+#[cfg(feature = "metrics")]
+struct TestMetric(String, Arc<Mutex<std::collections::BTreeMap<String, f64>>>);
+
+#[cfg(feature = "metrics")]
+impl metrics::CounterFn for TestMetric {
+    fn increment(&self, value: u64) {
+        *self.1.lock().unwrap().entry(self.0.clone()).or_default() += value as f64;
+    }
+    fn absolute(&self, value: u64) {
+        self.1.lock().unwrap().insert(self.0.clone(), value as f64);
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl metrics::HistogramFn for TestMetric {
+    fn record(&self, value: f64) {
+        *self.1.lock().unwrap().entry(self.0.clone()).or_default() += value;
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl TestRecorder {
+    fn metric(&self, key: &metrics::Key) -> Arc<TestMetric> {
+        let labels: Vec<String> = key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+        Arc::new(TestMetric(format!("{}{{{}}}", key.name(), labels.join(",")), self.0.clone()))
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl metrics::Recorder for TestRecorder {
+    fn describe_counter(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+    fn describe_gauge(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+    fn describe_histogram(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+    fn register_counter(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Counter {
+        metrics::Counter::from_arc(self.metric(key))
+    }
+    fn register_gauge(&self, _: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Gauge {
+        metrics::Gauge::noop()
+    }
+    fn register_histogram(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Histogram {
+        metrics::Histogram::from_arc(self.metric(key))
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_layer_records_fim_usage() {
+    use deepseek_rs::base_types::data::Pricing;
+    use deepseek_rs::middleware::MetricsLayer;
+
+    let recorder = TestRecorder::default();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    // 本地 recorder 只对当前线程有效，所以使用单线程运行时
+    metrics::with_local_recorder(&recorder, || {
+        runtime.block_on(async {
+            let (server, client) = setup().await;
+            let client = client.layer(MetricsLayer::new().default_pricing(Pricing::new(1.0, 2.0, 3.0)));
+            let mut response = fixtures::fim_response("fn main() {}");
+            response["usage"] = json!({
+                "prompt_tokens": 10,
+                "prompt_cache_hit_tokens": 4,
+                "prompt_cache_miss_tokens": 6,
+                "completion_tokens": 3,
+                "total_tokens": 13,
+                "prompt_tokens_details": { "cached_tokens": 4 }
+            });
+            server.push(Route::FimCompletions, MockResponse::json(response));
+            client.fim(&FimRequestBuilder::new("fn main").build().1).await.unwrap();
+
+            let mut chunks = vec![fixtures::fim_response("fn"), fixtures::fim_response(" main")];
+            chunks[0]["usage"] = json!(null);
+            chunks[0]["choices"][0]["finish_reason"] = json!(null);
+            server.push(Route::FimCompletions, MockResponse::sse(chunks));
+            let mut stream = client.fim_stream(&FimRequestBuilder::new("fn").build().1).await.unwrap();
+            while let Some(chunk) = stream.next().await {
+                chunk.unwrap();
+            }
+        })
+    });
+    let metrics = recorder.0.lock().unwrap();
+    assert_eq!(metrics["deepseek_prompt_tokens_total{model=deepseek-chat,cache=hit}"], 4.0);
+    assert_eq!(metrics["deepseek_prompt_tokens_total{model=deepseek-chat,cache=miss}"], 16.0);
+    assert_eq!(metrics["deepseek_completion_tokens_total{model=deepseek-chat}"], 3.0 + 5.0);
+    let cost = (4.0 + 6.0 * 2.0 + 3.0 * 3.0 + 10.0 * 2.0 + 5.0 * 3.0) / 1_000_000.0;
+    assert!((metrics["deepseek_request_cost{model=deepseek-chat}"] - cost).abs() < 1e-12);
+}
+
+#[tokio::test]
+async fn balance_watcher_skips_missing_currency() {
+    let (_server, client) = setup().await;
+    // 模拟服务器只返回 110 CNY
+    let mut events = BalanceWatcher::new(client)
+        .interval(Duration::from_secs(3600))
+        .threshold(Amount::usd(Decimal::new(5, 0)))
+        .threshold(Amount::cny(Decimal::new(200, 0)))
+        .spawn();
+    match events.recv().await {
+        Some(BalanceEvent::Low { threshold, .. }) => assert_eq!(threshold.currency, Currency::CNY),
+        other => panic!("{:?}", other),
+    }
+    assert!(tokio::time::timeout(Duration::from_millis(200), events.recv()).await.is_err());
+}
+
+#[tokio::test]
+async fn cassette_redacts_provider_auth_header() {
+    let server = MockServer::start().await.unwrap();
+    let path = std::env::temp_dir().join(format!("deepseek-cassette-{}.json", std::process::id()));
+    let provider = Provider::openai_compatible("proxy", &server.url()).auth(AuthScheme::Header(String::from("X-Proxy-Key")));
+    let client = DeepSeekClient::new("sk-secret")
+        .provider(provider)
+        .transport(RecordTransport::new(ReqwestTransport::new(), &path));
+    client.chat(&request("你好")).await.unwrap();
+    assert_eq!(server.requests_to(Route::ChatCompletions)[0].header("x-proxy-key"), Some("sk-secret"));
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let headers = cassette.interactions()[0].request().headers();
+    assert_eq!(headers.get("x-proxy-key").map(String::as_str), Some("[REDACTED]"));
+    assert!(!serde_json::to_string(&cassette).unwrap().contains("sk-secret"));
+}
+
+#[tokio::test]
+async fn strict_mode_checks_stream_chunks() {
+    let (server, client) = setup().await;
+    let drift = Arc::new(Mutex::new(Vec::new()));
+    let seen = drift.clone();
+    let client = client.strict(move |endpoint, d| seen.lock().unwrap().push((endpoint.to_string(), d.clone())));
+    let mut chunks = fixtures::chat_chunks("你好");
+    chunks[0]["service_tier"] = json!("default");
+    server.push(Route::ChatCompletions, MockResponse::sse(chunks));
+    let mut stream = client.chat_stream(&request("你好")).await.unwrap();
+    while let Some(chunk) = stream.next().await {
+        chunk.unwrap();
+    }
+    let drift = drift.lock().unwrap();
+    assert_eq!(
+        *drift,
+        vec![(String::from("/chat/completions"), Drift::Unknown(String::from("service_tier")))]
+    );
+}
@@ -0,0 +1,53 @@
+//! 在 wasm32 上测试 fetch 客户端和 SSE 解析，运行方法见 README 的“测试”一节。
+//! 测试在 Node.js 中运行，fetch 需要 Node.js 18 以上。
+#![cfg(target_arch = "wasm32")]
+use deepseek_rs::chat::*;
+use deepseek_rs::sse::JsonEventDecoder;
+use deepseek_rs::wasm::WasmClient;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const STREAM: &str = concat!(
+    "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"deepseek-chat\",",
+    "\"choices\":[{\"index\":0,\"delta\":{\"content\":\"你好\"},\"finish_reason\":null}]}\n\n",
+    "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":0,\"model\":\"deepseek-chat\",",
+    "\"choices\":[{\"index\":0,\"delta\":{\"content\":\"！\"},\"finish_reason\":\"stop\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn request() -> ChatRequest {
+    ChatRequestBuilder::new().add_message(Message::user_message("你好")).build().1
+}
+
+#[wasm_bindgen_test]
+fn sse_decoder_handles_split_chunks() {
+    // 逐字节输入，多字节字符也会被拆开
+    let mut decoder = JsonEventDecoder::<ChatCompletionChunk>::new();
+    let mut content = String::new();
+    for byte in STREAM.as_bytes() {
+        for chunk in decoder.feed(std::slice::from_ref(byte)) {
+            content.push_str(&chunk.unwrap().content().concat());
+        }
+    }
+    assert_eq!(content, "你好！");
+    assert!(decoder.is_done());
+    assert!(decoder.finish().is_ok());
+}
+
+#[wasm_bindgen_test]
+fn sse_decoder_reports_truncated_stream() {
+    let mut decoder = JsonEventDecoder::<ChatCompletionChunk>::new();
+    let truncated = &STREAM.as_bytes()[..STREAM.len() / 2];
+    assert!(decoder.feed(truncated).iter().all(Result::is_ok));
+    assert!(decoder.finish().is_err());
+}
+
+#[wasm_bindgen_test]
+async fn fetch_client_reports_network_errors() {
+    // 没有服务监听的端口，fetch 失败应该返回错误而不是 panic
+    let client = WasmClient::new("sk-test").base_url("http://127.0.0.1:9/");
+    assert_eq!(client.url("/models"), "http://127.0.0.1:9/models");
+    let err = client.chat(&request()).await.unwrap_err();
+    assert_eq!(err.status(), None);
+    assert!(client.chat_stream(&request()).await.is_err());
+    assert!(client.models().await.is_err());
+}